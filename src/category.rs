@@ -0,0 +1,32 @@
+//! USB Audio Function Category Codes from Universal Serial Bus Device Class
+//! Definition for Audio Devices, Release 2.0, Appendix A.7
+//!
+
+/// Audio Function Category written to the bCategory field of the AudioControl
+/// header, hinting the primary use of the audio function to the host
+#[rustfmt::skip]
+#[repr(u8)]
+#[non_exhaustive]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Category {
+    Undefined                       = 0x00,
+    DesktopSpeaker                  = 0x01,
+    HomeTheater                     = 0x02,
+    Microphone                      = 0x03,
+    Headset                         = 0x04,
+    Telephone                       = 0x05,
+    Converter                       = 0x06,
+    VoiceSoundRecorder              = 0x07,
+    IoBox                           = 0x08,
+    MusicalInstrument               = 0x09,
+    ProAudio                        = 0x0A,
+    AudioVideo                      = 0x0B,
+    ControlPanel                    = 0x0C,
+    Other                           = 0xFF,
+}
+
+impl From<Category> for u8 {
+    fn from(c: Category) -> u8 {
+        c as u8
+    }
+}
@@ -0,0 +1,86 @@
+//! Spatial locations of the channels in a cluster (bmChannelConfig) as defined
+//! in Universal Serial Bus Device Class Definition for Audio Devices, Release
+//! 2.0, section 4.1
+//!
+
+use core::ops::BitOr;
+
+/// A set of spatial locations, one bit per location. Channels carrying a
+/// location come first in the cluster, in bit order; combine locations with
+/// `|`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ChannelConfig(u32);
+
+#[rustfmt::skip]
+impl ChannelConfig {
+    pub const FRONT_LEFT: ChannelConfig                 = ChannelConfig(1 << 0);
+    pub const FRONT_RIGHT: ChannelConfig                = ChannelConfig(1 << 1);
+    pub const FRONT_CENTER: ChannelConfig               = ChannelConfig(1 << 2);
+    pub const LOW_FREQUENCY_EFFECTS: ChannelConfig      = ChannelConfig(1 << 3);
+    pub const BACK_LEFT: ChannelConfig                  = ChannelConfig(1 << 4);
+    pub const BACK_RIGHT: ChannelConfig                 = ChannelConfig(1 << 5);
+    pub const FRONT_LEFT_OF_CENTER: ChannelConfig       = ChannelConfig(1 << 6);
+    pub const FRONT_RIGHT_OF_CENTER: ChannelConfig      = ChannelConfig(1 << 7);
+    pub const BACK_CENTER: ChannelConfig                = ChannelConfig(1 << 8);
+    pub const SIDE_LEFT: ChannelConfig                  = ChannelConfig(1 << 9);
+    pub const SIDE_RIGHT: ChannelConfig                 = ChannelConfig(1 << 10);
+    pub const TOP_CENTER: ChannelConfig                 = ChannelConfig(1 << 11);
+    pub const TOP_FRONT_LEFT: ChannelConfig             = ChannelConfig(1 << 12);
+    pub const TOP_FRONT_CENTER: ChannelConfig           = ChannelConfig(1 << 13);
+    pub const TOP_FRONT_RIGHT: ChannelConfig            = ChannelConfig(1 << 14);
+    pub const TOP_BACK_LEFT: ChannelConfig              = ChannelConfig(1 << 15);
+    pub const TOP_BACK_CENTER: ChannelConfig            = ChannelConfig(1 << 16);
+    pub const TOP_BACK_RIGHT: ChannelConfig             = ChannelConfig(1 << 17);
+    pub const TOP_FRONT_LEFT_OF_CENTER: ChannelConfig   = ChannelConfig(1 << 18);
+    pub const TOP_FRONT_RIGHT_OF_CENTER: ChannelConfig  = ChannelConfig(1 << 19);
+    pub const LEFT_LOW_FREQUENCY_EFFECTS: ChannelConfig = ChannelConfig(1 << 20);
+    pub const RIGHT_LOW_FREQUENCY_EFFECTS: ChannelConfig = ChannelConfig(1 << 21);
+    pub const TOP_SIDE_LEFT: ChannelConfig              = ChannelConfig(1 << 22);
+    pub const TOP_SIDE_RIGHT: ChannelConfig             = ChannelConfig(1 << 23);
+    pub const BOTTOM_CENTER: ChannelConfig              = ChannelConfig(1 << 24);
+    pub const BACK_LEFT_OF_CENTER: ChannelConfig        = ChannelConfig(1 << 25);
+    pub const BACK_RIGHT_OF_CENTER: ChannelConfig       = ChannelConfig(1 << 26);
+    /// Raw data, not meant to be rendered; excludes every other location
+    pub const RAW_DATA: ChannelConfig                   = ChannelConfig(1 << 31);
+}
+
+impl ChannelConfig {
+
+    /// No spatial locations, leaving the host to place the channels
+    pub const fn none() -> ChannelConfig {
+        ChannelConfig(0)
+    }
+
+    /// A single front center channel
+    pub const fn mono() -> ChannelConfig {
+        ChannelConfig::FRONT_CENTER
+    }
+
+    /// Front left and front right channels
+    pub const fn stereo() -> ChannelConfig {
+        ChannelConfig(ChannelConfig::FRONT_LEFT.0 | ChannelConfig::FRONT_RIGHT.0)
+    }
+
+    pub const fn from_bits(bits: u32) -> ChannelConfig {
+        ChannelConfig(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+}
+
+impl BitOr for ChannelConfig {
+    type Output = ChannelConfig;
+
+    fn bitor(self, rhs: ChannelConfig) -> ChannelConfig {
+        ChannelConfig(self.0 | rhs.0)
+    }
+}
+
+impl From<ChannelConfig> for u32 {
+    fn from(config: ChannelConfig) -> u32 {
+        config.0
+    }
+}
@@ -140,6 +140,54 @@ pub const EP_CONTROL_UNDEFINED: u16 = 0x00;
 pub const SAMPLING_FREQ_CONTROL: u16 = 0x01;
 pub const PITCH_CONTROL: u16 = 0x02;
 
+// Audio 2.0 Class-Specific AC Interface Descriptor Subtypes
+pub const EFFECT_UNIT: u8 = 0x07;
+pub const CLOCK_SOURCE: u8 = 0x0A;
+pub const CLOCK_SELECTOR: u8 = 0x0B;
+pub const CLOCK_MULTIPLIER: u8 = 0x0C;
+pub const SAMPLE_RATE_CONVERTER: u8 = 0x0D;
+
+// Audio 2.0 Class-Specific Request Codes
+pub const CUR: u8 = 0x01;
+pub const RANGE: u8 = 0x02;
+pub const MEM: u8 = 0x03;
+
+// Audio 2.0 AudioControl Interface Control Selectors
+pub const AC_CONTROL_UNDEFINED: u8 = 0x00;
+pub const AC_LATENCY_CONTROL: u8 = 0x01;
+
+// Audio 2.0 Clock Source Control Selectors
+pub const CS_CONTROL_UNDEFINED: u8 = 0x00;
+pub const CS_SAM_FREQ_CONTROL: u8 = 0x01;
+pub const CS_CLOCK_VALID_CONTROL: u8 = 0x02;
+
+// Audio 2.0 Clock Selector Control Selectors
+pub const CX_CONTROL_UNDEFINED: u8 = 0x00;
+pub const CX_CLOCK_SELECTOR_CONTROL: u8 = 0x01;
+
+// Audio 2.0 Mixer Unit Control Selectors
+pub const MU_CONTROL_UNDEFINED: u8 = 0x00;
+pub const MU_MIXER_CONTROL: u8 = 0x01;
+
+// Audio 2.0 Selector Unit Control Selectors
+pub const SU_CONTROL_UNDEFINED: u8 = 0x00;
+pub const SU_SELECTOR_CONTROL: u8 = 0x01;
+
+// Audio 2.0 Clock Multiplier Control Selectors
+pub const CM_CONTROL_UNDEFINED: u8 = 0x00;
+pub const CM_NUMERATOR_CONTROL: u8 = 0x01;
+pub const CM_DENOMINATOR_CONTROL: u8 = 0x02;
+
+// Audio 2.0 Terminal Control Selectors
+pub const TE_CONNECTOR_CONTROL: u8 = 0x02;
+pub const TE_OVERLOAD_CONTROL: u8 = 0x03;
+pub const TE_CLUSTER_CONTROL: u8 = 0x04;
+
+// Audio 2.0 AudioStreaming Interface Control Selectors
+pub const AS_CONTROL_UNDEFINED: u8 = 0x00;
+pub const AS_ACT_ALT_SETTING_CONTROL: u8 = 0x01;
+pub const AS_VAL_ALT_SETTINGS_CONTROL: u8 = 0x02;
+
 // Format Type Codes
 pub const FORMAT_TYPE_UNDEFINED: u8 = 0;
 pub const FORMAT_TYPE_I: u8 = 0x01;
@@ -0,0 +1,104 @@
+//! Clock Source configuration as defined in Universal Serial Bus Device Class
+//! Definition for Audio Devices, Release 2.0, section 4.7.2.1. Every clock
+//! source entity of the function is built from one `ClockSource`; the sample
+//! rate it starts at is that of the stream it clocks.
+//!
+
+use crate::RatePolicy;
+
+/// Clock type, bits D1..0 of the Clock Source bmAttributes
+#[rustfmt::skip]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClockType {
+    External                = 0b00,
+    InternalFixed           = 0b01,
+    InternalVariable        = 0b10,
+    InternalProgrammable    = 0b11,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ClockSource<'a> {
+    pub(crate) clock_type: ClockType,
+    pub(crate) sof_sync: bool,
+    pub(crate) frequency_control: bool,
+    pub(crate) rates: &'a [u32],
+    pub(crate) rate_policy: RatePolicy,
+    pub(crate) name: Option<&'a str>,
+}
+
+impl ClockSource<'static> {
+
+    /// An internal fixed clock, not synchronized to SOF, with a frequency
+    /// control reporting the stream's rate and the `RatePolicy::Strict` policy
+    pub const fn new() -> ClockSource<'static> {
+        ClockSource {
+            clock_type: ClockType::InternalFixed,
+            sof_sync: false,
+            frequency_control: true,
+            rates: &[],
+            rate_policy: RatePolicy::Strict,
+            name: None,
+        }
+    }
+
+}
+
+impl<'a> ClockSource<'a> {
+
+    /// Set the clock type reported in bmAttributes. It only informs the host;
+    /// whether the rate can be changed is decided by `rates()`.
+    pub const fn clock_type(self, clock_type: ClockType) -> ClockSource<'a> {
+        ClockSource {
+            clock_type,
+            ..self
+        }
+    }
+
+    /// See `AudioClassBuilder::clock_sof_sync()`
+    pub const fn sof_sync(self, enabled: bool) -> ClockSource<'a> {
+        ClockSource {
+            sof_sync: enabled,
+            ..self
+        }
+    }
+
+    /// See `AudioClassBuilder::frequency_control()`
+    pub const fn frequency_control(self, enabled: bool) -> ClockSource<'a> {
+        ClockSource {
+            frequency_control: enabled,
+            ..self
+        }
+    }
+
+    /// See `AudioClassBuilder::sample_rates()`
+    pub const fn rates(self, rates: &'a [u32]) -> ClockSource<'a> {
+        ClockSource {
+            rates,
+            ..self
+        }
+    }
+
+    /// See `AudioClassBuilder::rate_policy()`
+    pub const fn rate_policy(self, policy: RatePolicy) -> ClockSource<'a> {
+        ClockSource {
+            rate_policy: policy,
+            ..self
+        }
+    }
+
+    /// Name the clock in its iClockSource string descriptor
+    pub const fn name(self, name: &'a str) -> ClockSource<'a> {
+        ClockSource {
+            name: Some(name),
+            ..self
+        }
+    }
+
+}
+
+impl Default for ClockSource<'static> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
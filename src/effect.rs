@@ -0,0 +1,69 @@
+//! Effect Unit as defined in Universal Serial Bus Device Class Definition for
+//! Audio Devices, Release 2.0, section 4.7.2.10. The unit only describes the
+//! effect to the host; its controls are answered by a user supplied handler.
+//!
+
+/// Effect Unit effect types, Appendix A.11
+#[rustfmt::skip]
+#[repr(u16)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum EffectType {
+    ParametricEq            = 0x0001,
+    Reverberation           = 0x0002,
+    ModulationDelay         = 0x0003,
+    DynamicRangeCompressor  = 0x0004,
+}
+
+/// A class request addressed to the effect unit
+#[derive(Clone, Copy, Debug)]
+pub struct EffectRequest {
+    /// Request code, 0x01 for CUR or 0x02 for RANGE
+    pub request: u8,
+    /// Control selector, as defined in Appendix A.17 for the effect type
+    pub selector: u8,
+    /// Channel number, 0 for the master channel
+    pub channel: u8,
+}
+
+/// The data phase of an effect request
+pub enum EffectAccess<'r> {
+    /// The host reads the control; fill the buffer with the response
+    Get(&'r mut [u8]),
+    /// The host writes the control with the given value
+    Set(&'r [u8]),
+}
+
+/// Answers a control request for the effect unit. For `Get` return the number
+/// of bytes written to the buffer, for `Set` return any `Some` to accept the
+/// value. Returning None stalls the request.
+///
+/// The handler runs inside `UsbDevice::poll` and must answer immediately:
+/// usb-device stalls any control transfer left unanswered when the classes
+/// return, so a response cannot be deferred until firmware fetches the value.
+/// Controls backed by slow peripherals, such as a potentiometer read over a
+/// bus, should be sampled from the main loop into a cache the handler answers
+/// from.
+pub type EffectHandler = fn(EffectRequest, EffectAccess) -> Option<usize>;
+
+#[derive(Clone, Copy)]
+pub struct EffectUnit {
+    pub(crate) effect_type: EffectType,
+    pub(crate) controls: u32,
+    pub(crate) handler: EffectHandler,
+}
+
+impl EffectUnit {
+
+    /// `controls` is the bmaControls bitmap of the master channel, two bits per
+    /// control selector starting at selector 1: 0b01 read only, 0b11 host
+    /// programmable.
+    pub fn new(effect_type: EffectType, controls: u32, handler: EffectHandler) -> EffectUnit {
+        EffectUnit {
+            effect_type,
+            controls,
+            handler,
+        }
+    }
+
+}
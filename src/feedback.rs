@@ -0,0 +1,110 @@
+//! Explicit feedback values as defined in Universal Serial Bus Specification,
+//! Revision 2.0, Section 5.12.4.2
+//!
+
+use crate::UsbSpeed;
+
+/// Size of a feedback packet: a 10.14 fixed point value at full speed, a
+/// 16.16 one at high speed
+pub fn packet_size(speed: UsbSpeed) -> u16 {
+    match speed {
+        UsbSpeed::Full => 3,
+        UsbSpeed::High => 4,
+    }
+}
+
+/// bInterval of a feedback endpoint refreshed every 2^`refresh` ms, polling
+/// every 2^(bInterval - 1) frames at full speed or microframes at high speed
+pub fn interval(refresh: u8, speed: UsbSpeed) -> u8 {
+    match speed {
+        UsbSpeed::Full => refresh + 1,
+        UsbSpeed::High => refresh + 4,
+    }
+}
+
+// samples per (micro)frame in fixed point are Hz << shift / 1000: 10.14 per
+// 1 ms frame, or 16.16 per 125 us microframe, which is Hz << 16 / 8000
+fn shift(speed: UsbSpeed) -> u32 {
+    match speed {
+        UsbSpeed::Full => 14,
+        UsbSpeed::High => 13,
+    }
+}
+
+/// Encode a sample rate in Hz as samples per frame (full speed) or
+/// microframe (high speed) in the speed's fixed point format
+pub fn encode(rate: u32, speed: UsbSpeed) -> u32 {
+    encode_ppm(rate, 0, speed)
+}
+
+/// Encode a rate that deviates from `nominal` Hz by `ppm` parts per million,
+/// as samples per frame or microframe. Rates of the 44.1 kHz family are not
+/// a whole number of samples per frame; their fraction is rounded to the
+/// nearest step, e.g. 44100 Hz to 0x0B0666 in 10.14. The result saturates
+/// at 0 and `u32::MAX`.
+pub fn encode_ppm(nominal: u32, ppm: i32, speed: UsbSpeed) -> u32 {
+    // a u32 rate shifted by 14 times an i32 offset does not fit an i64
+    let scaled = ((nominal as i128) << shift(speed)) * (1_000_000 + ppm as i128);
+
+    ((scaled + 500_000_000) / 1_000_000_000).clamp(0, u32::MAX as i128) as u32
+}
+
+/// Decode a samples per frame or microframe value back to a sample rate in Hz
+pub fn decode(value: u32, speed: UsbSpeed) -> u32 {
+    ((value as u64 * 1000) >> shift(speed)) as u32
+}
+
+/// Deviation of a measured rate from the nominal rate in parts per million,
+/// rounded to the nearest whole PPM. Positive when the clock runs fast.
+pub fn ppm_error(measured_hz: f32, nominal: u32) -> i32 {
+    if nominal == 0 {
+        return 0;
+    }
+
+    let ppm = (measured_hz - nominal as f32) / nominal as f32 * 1_000_000.0;
+
+    // no_std has no f32::round, so round half away from zero by hand
+    if ppm < 0.0 { (ppm - 0.5) as i32 } else { (ppm + 0.5) as i32 }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn ppm_error_of_a_fast_clock() {
+        assert_eq!(ppm_error(48004.8, 48000), 100);
+        assert_eq!(ppm_error(44104.41, 44100), 100);
+        assert_eq!(ppm_error(47995.2, 48000), -100);
+        assert_eq!(ppm_error(48000.0, 0), 0);
+    }
+
+    #[test]
+    fn encodes_a_fast_clock() {
+        let ppm = ppm_error(48004.8, 48000);
+
+        // 48004.8 Hz is 48.0048 samples per frame, 786510.6 in 10.14
+        assert_eq!(encode_ppm(48000, ppm, UsbSpeed::Full), 786511);
+        assert_eq!(decode(encode_ppm(48000, ppm, UsbSpeed::High), UsbSpeed::High), 48004);
+        assert_eq!(encode(44100, UsbSpeed::Full), 0x0B0666);
+    }
+
+    #[test]
+    fn rounds_the_44_1_khz_family() {
+        // 44.1, 88.2 and 176.4 samples per frame
+        assert_eq!(encode(44100, UsbSpeed::Full), 0x0B0666);
+        assert_eq!(encode(88200, UsbSpeed::Full), 0x160CCD);
+        assert_eq!(encode(176400, UsbSpeed::Full), 0x2C199A);
+    }
+
+    #[test]
+    fn saturates_at_the_extremes() {
+        assert_eq!(encode_ppm(u32::MAX, i32::MAX, UsbSpeed::Full), u32::MAX);
+        assert_eq!(encode_ppm(u32::MAX, 0, UsbSpeed::Full), u32::MAX);
+        assert_eq!(encode_ppm(48000, -1_000_000, UsbSpeed::Full), 0);
+        assert_eq!(encode_ppm(48000, i32::MIN, UsbSpeed::Full), 0);
+    }
+
+}
@@ -1,8 +1,9 @@
 #![no_std]
-#[allow(unused_parens)]
+#![allow(unused_parens)]
 
 /// INCLUDES
 use usb_device::{
+    UsbDirection,
     UsbError,
     class_prelude::*,
     endpoint::{
@@ -21,24 +22,49 @@ use usb_device::{
     }
 };
 
-use core::marker::PhantomData;
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+};
 
 // LOCAL INCLUDES
+mod category;
+mod channel_config;
 mod class_codes;
+mod clock;
+mod effect;
+mod feedback;
+mod pacer;
+mod packet;
+mod sample_rate;
+mod status;
 mod terminal_type;
+mod uac1;
 
+pub use category::Category;
+pub use channel_config::ChannelConfig;
 use class_codes::*;
+pub use clock::{ClockSource, ClockType};
+pub use effect::{EffectAccess, EffectHandler, EffectRequest, EffectType, EffectUnit};
+pub use feedback::ppm_error;
+pub use pacer::FramePacer;
+pub use packet::PacketView;
+pub use sample_rate::SampleRate;
+use status::{StatusQueue, STATUS_PACKET_SIZE};
 pub use terminal_type::TerminalType;
 use usb_device::{
     descriptor::descriptor_type::INTERFACE,
     endpoint::{
-        IsochronousSynchronizationType::Asynchronous,
-        IsochronousUsageType::{Data, ImplicitFeedbackData},
+        IsochronousSynchronizationType,
+        IsochronousSynchronizationType::{Asynchronous, NoSynchronization},
+        IsochronousUsageType,
+        IsochronousUsageType::{Data, Feedback, ImplicitFeedbackData},
     },
 };
 
 // CONSTANTS
 const ID_CLOCK_SRC: u8 = 0x01;
+const ID_OUTPUT_CLOCK_SRC: u8 = 0x06;
 
 const ID_INPUT_TERMINAL: u8 = 0x02;
 const ID_INPUT_STREAMING: u8 = 0x03;
@@ -46,571 +72,4731 @@ const ID_INPUT_STREAMING: u8 = 0x03;
 const ID_OUTPUT_TERMINAL: u8 = 0x05;
 const ID_OUTPUT_STREAMING: u8 = 0x04;
 
+const ID_INPUT_SINK: u8 = 0x07;
+
+const ID_EFFECT_UNIT: u8 = 0x08;
+const ID_FEATURE_UNIT: u8 = 0x09;
+const ID_CLOCK_MULTIPLIER: u8 = 0x0A;
+const ID_CLOCK_SELECTOR: u8 = 0x0B;
+
+// clock sources the selector offers besides ID_CLOCK_SRC
+const MAX_SELECTABLE_CLOCKS: usize = 3;
+const ID_SELECTABLE_CLOCK_SRC: [u8; MAX_SELECTABLE_CLOCKS] = [0x0C, 0x0D, 0x0E];
+
+const ID_SELECTOR_UNIT: u8 = 0x0F;
+
+// input terminals the selector unit offers besides ID_INPUT_TERMINAL
+const MAX_SELECTABLE_INPUTS: usize = 3;
+const ID_SELECTABLE_INPUT_TERMINAL: [u8; MAX_SELECTABLE_INPUTS] = [0x10, 0x11, 0x12];
+
+const ID_MIXER_UNIT: u8 = 0x13;
+
+// input terminals the mixer unit mixes in besides its first input
+const MAX_MIXER_INPUTS: usize = 3;
+const ID_MIXER_INPUT_TERMINAL: [u8; MAX_MIXER_INPUTS] = [0x14, 0x15, 0x16];
+
+// mixer levels held, one per input channel and output channel pair
+const MAX_MIXER_CONTROLS: usize = 64;
+
+// a mixer level of -infinity dB, cutting the input channel off
+const MIXER_SILENT: i16 = i16::MIN;
+
+// volume control range in 1/256 dB steps
+const VOLUME_MIN: i16 = -60 * 256;
+const VOLUME_MAX: i16 = 0;
+const VOLUME_RES: i16 = 256;
+
 
 
 // ERROR DEFINITIONS
 #[derive(Debug)]
 pub enum Error{
     UsbError(UsbError),
-    StreamNotInitialized
+    StreamNotInitialized,
+    StreamIdle,
+    ControlNotEnabled,
+    InvalidAlignment,
+    InvalidResolution,
+    InvalidInterval,
+    /// A stream needs 1 to 254 channels
+    InvalidChannelCount,
+    /// A stream's sample rate must be non-zero
+    InvalidSampleRate,
+    /// The isochronous endpoints need `required` bytes per (micro)frame but
+    /// the bus speed allows only `available`
+    BandwidthExceeded { required: u16, available: u16 },
+    /// The function category contradicts the configured terminal types, e.g.
+    /// `Headset` without a microphone input and headphone output
+    CategoryMismatch,
+    /// The input and output streams have different sample rates but share
+    /// one clock source, see `AudioClassBuilder::independent_clocks()`
+    ClockRateMismatch,
+    /// More clock sources were given to the clock selector than it supports
+    TooManyClockSources,
+    /// No clock source of the function has the given ID, see
+    /// `AudioClass::set_clock_valid()`
+    UnknownClock,
+    /// More input terminals were given to the selector or mixer unit than it
+    /// supports
+    TooManyInputTerminals,
+    /// The mixer unit has more input and output channel pairs than it can
+    /// hold levels for
+    MixerTooLarge,
+    /// The configuration descriptor needs `required` bytes but the control
+    /// buffer holds only `available`
+    DescriptorOverflow { required: usize, available: usize },
+    /// Status interrupt messages are queued faster than the host polls for
+    /// them; the message was dropped
+    StatusQueueFull,
+    /// A configured feature has no Audio Class 1.0 equivalent, see
+    /// `AudioClassBuilder::audio_1_0()`
+    UnsupportedInAudio10,
+    /// Data handed to `write_frame()` is not a whole number of audio frames
+    /// of the selected format
+    PartialFrame,
 }
 type Result<T> = core::result::Result<T, Error>;
 
 
 
+/// CONTROL RESPONSES
+/// Answer a GET request with at most the wLength bytes the host asked for.
+/// Every response goes through here so a fixed size answer never overruns a
+/// shorter request, whatever the control pipe does with the excess. A request
+/// for fewer bytes than the control has gets the leading part, as for the
+/// wNumSubRanges of a RANGE.
+fn accept_truncated<B: UsbBus>(xfer: ControlIn<B>, data: &[u8]) -> usb_device::Result<()> {
+    let length = data.len().min(xfer.request().length as usize);
+
+    xfer.accept_with(&data[..length])
+}
+
+
+
+/// CONTROL REQUEST KINDS
+/// The kind of control request most recently answered by the class. Useful for
+/// logging enumeration progress and diagnosing requests that are never answered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ControlKind {
+    SetInterface,
+    GetInterface,
+    ClockRange,
+    ClockCur,
+    ClockValid,
+    CopyProtect,
+    Effect,
+    AltSettings,
+    Cluster,
+    Mute,
+    Volume,
+    ClockMultiplier,
+    ClockSelector,
+    InputSelector,
+    Mixer,
+    Latency,
+}
+
+/// A stream the host opened or closed through SET_INTERFACE (or a bus reset)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StreamChange {
+    /// `In` for the input stream, `Out` for the output stream
+    pub direction: UsbDirection,
+    /// The streaming interface number
+    pub interface: u8,
+    /// The alternate setting now selected, 0 when the stream is closed
+    pub alt_setting: u8,
+}
+
+/// A packet read with `AudioClass::read_frame()`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FrameRead {
+    /// Complete audio frames read, a sample of every channel each
+    pub frames: usize,
+    /// Bytes after the last complete frame. Non-zero means the host sent a
+    /// partial frame, so its idea of the format or channel count differs from
+    /// the descriptors'.
+    pub leftover: usize,
+}
+
+/// A class-specific control request the class answers, as listed by
+/// `AudioClass::control_routes()`
+#[cfg(feature = "diagnostics")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ControlRoute {
+    /// wIndex: entity ID in the high byte, interface number in the low byte
+    pub index: u16,
+    /// Control selector, or None when every selector is forwarded
+    pub selector: Option<u8>,
+    /// Request code, 0x01 for CUR or 0x02 for RANGE
+    pub request: u8,
+    /// GET requests are `In`, SET requests are `Out`
+    pub direction: UsbDirection,
+    pub kind: ControlKind,
+}
+
+
+
 /// STREAM CONFIG
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Format {
+    /// 8 bit unsigned, carried as PCM8
+    U8,
     S16LE,
+    /// 24 bit samples packed in 3 byte subslots
     S24LE,
+    /// 24 bit samples in 4 byte subslots, the valid bits in the upper three
+    /// bytes, as I2S peripherals commonly deliver them
+    S24In32LE,
+    S32LE,
+    /// 32 bit IEEE 754 float
+    F32LE,
 }
 
 impl Format {
 
-    fn size(&self) -> u8 {
+    const fn size(&self) -> u8 {
         match self {
+            Format::U8 => 1,
             Format::S16LE => 2,
             Format::S24LE => 3,
+            Format::S24In32LE => 4,
+            Format::S32LE => 4,
+            Format::F32LE => 4,
         }
     }
 
-    fn res(&self) -> u8 {
+    const fn res(&self) -> u8 {
         match self {
+            Format::U8 => 8,
             Format::S16LE => 16,
             Format::S24LE => 24,
+            Format::S24In32LE => 24,
+            Format::S32LE => 32,
+            Format::F32LE => 32,
+        }
+    }
+
+    // Type I bmFormats bit, Appendix A.2.1 of the Audio Data Formats spec
+    fn bm_format(&self) -> u32 {
+        match self {
+            Format::U8 => 1 << 1, // PCM8
+            Format::F32LE => 1 << 2, // IEEE_FLOAT
+            Format::S16LE | Format::S24LE | Format::S24In32LE | Format::S32LE => 1 << 0, // PCM
+        }
+    }
+
+    // Type I wFormatTag of Audio Class 1.0, Appendix A.1.1 of the Audio Data
+    // Formats spec
+    fn format_tag(&self) -> u16 {
+        match self {
+            Format::U8 => PCM8,
+            Format::F32LE => IEEE_FLOAT,
+            Format::S16LE | Format::S24LE | Format::S24In32LE | Format::S32LE => PCM,
         }
     }
 
 }
 
-pub struct StreamConfig<'a, const R: u16> {
+pub struct StreamConfig<'a> {
     format: Format,
     term_type: TerminalType,
     n_channels: u8,
+    rate: u32,
+    alignment: u16,
+    headroom: u16,
+    interval: u8,
+    resolution: u8,
+    terminal_cluster: Option<Cluster>,
+    channel_config: ChannelConfig,
+    terminal_name: Option<&'a str>,
+    channel_names: &'a [&'a str],
+    formats: &'a [Format],
+    user_data: u32,
     marker: PhantomData<&'a u8>,
 }
 
-impl<'a, const R:u16> StreamConfig<'a, R> {
+impl<'a> StreamConfig<'a> {
 
-    pub fn new(format: Format, n_channels: u8, term_type: TerminalType) -> Result<StreamConfig<'a, R>>{
-        Ok(
-            StreamConfig {
-                format,
-                n_channels,
-                term_type,
-                marker: PhantomData
-            }
-        )
-    }
+    /// Returns `InvalidChannelCount` unless there are 1 to 254 channels,
+    /// `InvalidSampleRate` for a zero rate, and `BandwidthExceeded` if a
+    /// packet would not fit an isochronous endpoint even at high speed, since
+    /// each describes a stream no host would enumerate. Whether it fits the
+    /// speed the class runs at is checked by `AudioClassBuilder::build()`.
+    pub fn new(format: Format, n_channels: u8, term_type: TerminalType, rate: u32) -> Result<StreamConfig<'a>>{
+        if n_channels == 0 || n_channels == u8::MAX {
+            return Err(Error::InvalidChannelCount);
+        }
 
-    pub fn packet_size(&self) -> u16 {
-        // number of bytes for one sample
-        let size = self.format.size() * self.n_channels;
+        if rate == 0 {
+            return Err(Error::InvalidSampleRate);
+        }
 
-        // this integer division causes a necessary floor round
-        let samples = (R / 1000);
+        let config = StreamConfig::unchecked(format, n_channels, term_type, rate);
 
-        // we need to satisfy n + 1 audio samples as the maximum for feedback compensation
-        (samples + 1) * size
+        config.check_packet_size()?;
+
+        Ok(config)
     }
 
-}
+    /// `new()` for `const` contexts, such as stream layouts declared as
+    /// associated constants. Panics on input `new()` would reject, which in a
+    /// `const` item fails the build rather than the firmware.
+    pub const fn const_new(format: Format, n_channels: u8, term_type: TerminalType, rate: u32) -> StreamConfig<'a> {
+        assert!(n_channels != 0 && n_channels != u8::MAX, "a stream needs 1 to 254 channels");
+        assert!(rate != 0, "a stream's sample rate must be non-zero");
 
+        let config = StreamConfig::unchecked(format, n_channels, term_type, rate);
 
+        // as check_packet_size(), with only the one format
+        let required = config.packet_samples(rate, UsbSpeed::High) as u64 * format.size() as u64 * n_channels as u64;
+        assert!(required <= UsbSpeed::High.iso_budget() as u64, "a packet must fit a high speed isochronous endpoint");
 
-/// AUDIO STREAM
-pub struct AudioStream<'a, B: UsbBus, D: EndpointDirection, const R: u16> {
-    stream_config: StreamConfig<'a, R>,
-    interface: InterfaceNumber,
-    endpoint: Endpoint<'a, B, D>,
-    alt_setting: u8,
-}
+        config
+    }
 
-impl<'a, B: UsbBus, D: EndpointDirection, const R: u16> AudioStream<'a, B, D, R> {
+    const fn unchecked(format: Format, n_channels: u8, term_type: TerminalType, rate: u32) -> StreamConfig<'a> {
+        StreamConfig {
+            format,
+            n_channels,
+            term_type,
+            rate,
+            alignment: 1,
+            headroom: 1,
+            interval: 1,
+            resolution: format.res(),
+            terminal_cluster: None,
+            channel_config: ChannelConfig::none(),
+            terminal_name: None,
+            channel_names: &[],
+            formats: &[],
+            user_data: 0,
+            marker: PhantomData
+        }
+    }
 
-    fn input_ac_descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+    /// Pad the endpoint max packet size up to a multiple of `alignment` bytes,
+    /// for controllers that require aligned isochronous buffers. The alignment
+    /// must be a non-zero power of two. Only the advertised maximum grows; the
+    /// host still sends or expects packets sized by the actual sample count.
+    /// Returns `BandwidthExceeded` if the padded packet no longer fits an
+    /// isochronous endpoint, as for `new()`.
+    pub fn with_alignment(self, alignment: u16) -> Result<StreamConfig<'a>> {
+        if !alignment.is_power_of_two() {
+            return Err(Error::InvalidAlignment);
+        }
 
-        let input_type: [u8; 2] = self.stream_config.term_type.as_bytes();
-        let output_type: [u8; 2] = TerminalType::UsbStreaming.as_bytes();
+        let config = StreamConfig {
+            alignment,
+            ..self
+        };
 
-        writer.write(CS_INTERFACE, &[
-            INPUT_TERMINAL,
-            ID_INPUT_TERMINAL, // terminal ID
-            input_type[0], // terminal type
-            input_type[1],
-            0x00, // associated terminal (no assoc)
-            ID_CLOCK_SRC, // clock source ID
-            self.stream_config.n_channels, // logical channels
-            0x00, 0x00, 0x00, 0x00, // spacial description config
-            0x00, // string index (none)
-            0x00, 0x00, // bmControls (none)
-            0x00, // terminal desc string index (none)
-        ]).unwrap();
+        config.check_packet_size()?;
 
-        writer.write(CS_INTERFACE, &[
-            OUTPUT_TERMINAL,
-            ID_INPUT_STREAMING, // terminal ID
-            output_type[0], // terminal type
-            output_type[1],
-            0x00, // associated terminal (none)
-            ID_INPUT_TERMINAL, // source ID (the above input terminal)
-            ID_CLOCK_SRC, // clock source ID (none)
-            0x00, // bmControls (none)
-            0x00,
-            0x00, // terminal desc string index (none)
-        ]).unwrap();
+        Ok(config)
+    }
 
-        Ok(())
+    /// Size the endpoint for `samples` extra samples per frame beyond the
+    /// nominal count instead of the default 1, to absorb hosts that overshoot
+    /// by more than feedback asks for. Returns `BandwidthExceeded` if the
+    /// padded packet no longer fits an isochronous endpoint, as for `new()`.
+    pub fn with_headroom(self, samples: u16) -> Result<StreamConfig<'a>> {
+        let config = StreamConfig {
+            headroom: samples,
+            ..self
+        };
+
+        config.check_packet_size()?;
 
+        Ok(config)
     }
 
-    fn output_ac_descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+    /// Poll the data endpoint every 2^(`interval` - 1) frames instead of every
+    /// frame (`interval` 1, the default), as the endpoint's bInterval. Each
+    /// packet then carries that many frames' worth of samples, so the endpoint
+    /// grows accordingly. The interval must be in 1..=16; returns
+    /// `BandwidthExceeded` if the larger packet no longer fits an isochronous
+    /// endpoint, as for `new()`.
+    pub fn with_interval(self, interval: u8) -> Result<StreamConfig<'a>> {
+        if !(1..=16).contains(&interval) {
+            return Err(Error::InvalidInterval);
+        }
 
-        let input_type: [u8; 2] = TerminalType::UsbStreaming.as_bytes();
-        let output_type: [u8; 2] = self.stream_config.term_type.as_bytes();
+        let config = StreamConfig {
+            interval,
+            ..self
+        };
 
-        writer.write(CS_INTERFACE, &[
-            INPUT_TERMINAL,
-            ID_OUTPUT_STREAMING, // terminal ID
-            input_type[0], // terminal type
-            input_type[1],
-            0x00, // associated terminal (no assoc)
-            ID_CLOCK_SRC, // clock source ID
-            self.stream_config.n_channels, // logical channels
-            0x00, 0x00, 0x00, 0x00, // spacial description config
-            0x00, // string index (none)
-            0x00, 0x00, //bmControls (none)
-            0x00, // terminal desc string index (none)
-        ]).unwrap();
+        config.check_packet_size()?;
 
-        writer.write(CS_INTERFACE, &[
-            OUTPUT_TERMINAL,
-            ID_OUTPUT_TERMINAL, // terminal ID
-            output_type[0], // terminal type
-            output_type[1],
-            0x00, // associated terminal (none)
-            ID_OUTPUT_STREAMING, //source ID (the above input terminal)
-            ID_CLOCK_SRC, // clock source ID (none)
-            0x00, // bmControls (none)
-            0x00,
-            0x00, // terminal desc string index (none)
-        ]).unwrap();
+        Ok(config)
+    }
+
+    // a packet at the configured rate, padded as configured, must fit an
+    // isochronous endpoint at the speed with the most room; build() checks
+    // the speed the class actually runs at
+    fn check_packet_size(&self) -> Result<()> {
+        let required = self.packet_size_for(&[], UsbSpeed::High);
+        let available = UsbSpeed::High.iso_budget();
+
+        if required > available {
+            return Err(Error::BandwidthExceeded { required, available });
+        }
 
         Ok(())
     }
 
-    fn input_as_ep_descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+    /// Report fewer valid bits than the format's container holds, e.g. 20 bit
+    /// samples in the 4 byte container of `S32LE`. The resolution must be
+    /// non-zero and fit in the container.
+    pub fn with_resolution(self, resolution: u8) -> Result<StreamConfig<'a>> {
+        if resolution == 0 || resolution > self.format.res() {
+            return Err(Error::InvalidResolution);
+        }
 
-        // AUDIO STREAMING DESCRIPTORS
-        //TODO check the protocol value (IP_VERSION_02_00)
-        writer.interface(self.interface, AUDIO, AUDIOSTREAMING, IP_VERSION_02_00).unwrap();
-
-        writer.write(INTERFACE, &[
-            self.interface.into(),
-            0x01, // alternate setting
-            0x01, // n endpoints (1 data endpoint)
-            AUDIO,
-            AUDIOSTREAMING,
-            IP_VERSION_02_00,
-            0x00,
-        ]).unwrap();
+        Ok(
+            StreamConfig {
+                resolution,
+                ..self
+            }
+        )
+    }
 
-        writer.write(CS_INTERFACE, &[
-            AS_GENERAL,
-            ID_INPUT_STREAMING, // input interface ID (USB streaming)
-            0x00, // bmControls
-            0x01, // format type I
-            0x01, 0x00, 0x00, 0x00, // audio data formats (PCM only)
-            self.stream_config.n_channels,
-            0x00, 0x00, 0x00, 0x00, // spacial location description (none)
-            0x00, // string index (none)
-        ]).unwrap();
+    /// Give an input stream's physical input terminal its own channel cluster,
+    /// independent of the channels carried on USB. A beamforming device, for
+    /// example, reports a 4 channel microphone array feeding a 1 channel
+    /// stream. Without it the terminal has the stream's channel count.
+    pub fn with_terminal_cluster(self, cluster: Cluster) -> StreamConfig<'a> {
+        StreamConfig {
+            terminal_cluster: Some(cluster),
+            ..self
+        }
+    }
 
-        writer.write(CS_INTERFACE, &[
-            FORMAT_TYPE,
-            FORMAT_TYPE_I,
-            self.stream_config.format.size(),
-            self.stream_config.format.res(),
-        ]).unwrap();
-
-        // ENDPOINT DESCRIPTORS
-        /*
-        The standard writer endpoint function doesn't allow for the custom bmAttributes
-        necessary for implicit feedback, or to define the synchronisation type. So,
-        this is done manually with the fields filled from the endpoint where needed.
-         */
-        let max_transfer: [u8; 2] = self.stream_config.packet_size().to_be_bytes();
-
-        writer.write(0x05, &[
-            self.endpoint.address().into(),
-            0b00100101, // bmAttributes: Isochronous, Implicit FB, Asynchronous
-            max_transfer[1],
-            max_transfer[0],
-            self.endpoint.interval(),
-        ]).unwrap();
-
-        writer.write(CS_ENDPOINT, &[
-            EP_GENERAL,
-            0x00, // bmAttributes
-            0x00, // bmControls
-            0x00, // bLockDelayUnits
-            0x00, 0x00 // wLockDelay
-        ]).unwrap();
+    /// Report the spatial location of the stream's channels (bmChannelConfig),
+    /// e.g. `ChannelConfig::stereo()`, in its terminals and AS_GENERAL. By
+    /// default no locations are given and hosts guess the channel mapping.
+    pub fn with_channel_config(self, channel_config: ChannelConfig) -> StreamConfig<'a> {
+        StreamConfig {
+            channel_config,
+            ..self
+        }
+    }
 
-        Ok(())
+    /// Name the stream's physical terminal (iTerminal), which hosts show as
+    /// the device's input or output name.
+    pub fn with_terminal_name(self, name: &'a str) -> StreamConfig<'a> {
+        StreamConfig {
+            terminal_name: Some(name),
+            ..self
+        }
+    }
 
+    /// Name the stream's logical channels in order (iChannelNames), which
+    /// hosts show per channel, separately from the terminal name. Each channel
+    /// gets a string index of its own, consecutive as the descriptors require,
+    /// so `names` should cover every channel; a channel without a name has
+    /// its string request stalled.
+    pub fn with_channel_names(self, names: &'a [&'a str]) -> StreamConfig<'a> {
+        StreamConfig {
+            channel_names: names,
+            ..self
+        }
     }
 
-    fn output_as_ep_descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+    // allocate one string index per logical channel, returning the first
+    fn alloc_channel_names<B: UsbBus>(&self, allocator: &UsbBusAllocator<B>) -> Option<StringIndex> {
+        if self.channel_names.is_empty() {
+            return None;
+        }
 
-        // AUDIO STREAMING DESCRIPTORS
-        writer.interface(self.interface, AUDIO, AUDIOSTREAMING, IP_UNDEFINED).unwrap();
-
-        writer.write(INTERFACE, &[
-            self.interface.into(),
-            0x01, // alternate setting
-            0x01, // n endpoints (1 data endpoint)
-            AUDIO,
-            AUDIOSTREAMING,
-            IP_VERSION_02_00,
-            0x00,
-        ]).unwrap();
+        let first = allocator.string();
 
-        writer.write(CS_INTERFACE, &[
-            AS_GENERAL,
-            ID_OUTPUT_STREAMING,
-            0x00,
-            0x01,
-            0x01, 0x00, 0x00, 0x00,
-            self.stream_config.n_channels,
-            0x00, 0x00, 0x00, 0x00,
-            0x00,
-        ]).unwrap();
+        for _ in 1..self.n_channels {
+            allocator.string();
+        }
 
-        writer.write(CS_INTERFACE, &[
-            FORMAT_TYPE,
-            FORMAT_TYPE_I,
-            self.stream_config.format.size(),
-            self.stream_config.format.res(),
-        ]).unwrap();
-
-        let max_transfer: [u8; 2] = self.stream_config.packet_size().to_be_bytes();
-
-        writer.write(0x05, &[
-            self.endpoint.address().into(),
-            0b00000101, // bmAttributes: Isochronous, Asynchronous
-            max_transfer[1],
-            max_transfer[0],
-            self.endpoint.interval(),
-        ]).unwrap();
-
-        writer.write(CS_ENDPOINT, &[
-            EP_GENERAL,
-            0x00, // bmAttributes
-            0x00, // bmControls
-            0x00, // bLockDelayUnits
-            0x00, 0x00 // wLockDelay
-        ]).unwrap();
+        Some(first)
+    }
 
-        Ok(())
+    /// Offer further formats besides the one the stream was created with, for
+    /// the host to pick from. Formats sharing a subslot size and resolution,
+    /// such as `S32LE` and `F32LE`, share an alternate setting with their
+    /// bmFormats bits combined; every other format gets an alternate setting
+    /// of its own, numbered from 2 in order of appearance. At most 7
+    /// alternate settings can be reported as valid. The endpoint is sized for
+    /// the largest format, so returns `BandwidthExceeded` if its packet no
+    /// longer fits an isochronous endpoint, as for `new()`.
+    pub fn with_formats(self, formats: &'a [Format]) -> Result<StreamConfig<'a>> {
+        let config = StreamConfig {
+            formats,
+            ..self
+        };
+
+        config.check_packet_size()?;
 
+        Ok(config)
     }
 
-}
+    /// Attach a value of the application's choosing to the stream, such as a
+    /// DMA channel handle, to be read back from the class with
+    /// `input_user_data()` or `output_user_data()`. Defaults to 0.
+    pub fn with_user_data(self, user_data: u32) -> StreamConfig<'a> {
+        StreamConfig {
+            user_data,
+            ..self
+        }
+    }
+
+    /// Nominal bytes carried per 1 ms frame at the configured rate, rounded
+    /// down for rates that are not a multiple of 1000 Hz (`FramePacer` gives
+    /// the exact per frame sample count). Unlike `packet_size()` this has no
+    /// headroom for the extra sample feedback may ask for, so it suits buffer
+    /// sizing rather than the endpoint maximum.
+    pub fn bytes_per_frame(&self) -> u16 {
+        ((self.rate / 1000) * self.format.size() as u32 * self.n_channels as u32) as u16
+    }
+
+    /// wMaxPacketSize of the stream's endpoint: the samples per packet
+    /// rounded up, plus the headroom, padded to the alignment. At 44.1 kHz
+    /// and the default interval this is 45 + 1 samples per channel.
+    pub fn packet_size(&self) -> u16 {
+        self.packet_size_for(&[], UsbSpeed::Full)
+    }
 
+    // packet size at the highest of the stream's rate and `rates`, which the
+    // host may select
+    fn packet_size_for(&self, rates: &[u32], speed: UsbSpeed) -> u16 {
+        let rate = rates.iter().fold(self.rate, |max, &rate| max.max(rate));
 
+        // number of bytes for one sample of the largest format
+        let size = self.max_size() as u32 * self.n_channels as u32;
 
-/// AUDIO CLASS
-pub struct AudioClass<'a, B: UsbBus, const R: u16> {
-    control_interface: InterfaceNumber,
-    input: Option<AudioStream<'a, B, In, R>>,
-    output: Option<AudioStream<'a, B, Out, R>>,
-    clock_index: u8,
-}
+        let max = self.packet_samples(rate, speed).saturating_mul(size).min(u16::MAX as u32) as u16;
 
-impl<B: UsbBus, const R: u16> AudioClass<'_, B, R> {
+        // pad up to the controller buffer alignment
+        max.next_multiple_of(self.alignment)
+    }
 
-    /// Read audio frames as output by the host. Returns an Error if no output
-    /// stream has been configured.
-    pub fn read(&self, data: &mut [u8]) -> Result<usize> {
+    // most samples per channel in one packet at `rate`
+    const fn packet_samples(&self, rate: u32, speed: UsbSpeed) -> u32 {
+        let frames: u64 = 1 << (self.interval - 1);
 
-        if let Some(ref output) = self.output {
-            output.endpoint.read(data).map_err(Error::UsbError)
-        } else {
-            Err(Error::StreamNotInitialized)
-        }
+        // round up: a fractional rate such as 44.1 kHz alternates between 44
+        // and 45 samples per frame, and the endpoint must hold the larger
+        let samples = (rate as u64 * frames).div_ceil(speed.frames_per_second());
+
+        // we need to satisfy n + 1 audio samples as the maximum for feedback compensation
+        let samples = samples + self.headroom as u64;
 
+        if samples > u32::MAX as u64 { u32::MAX } else { samples as u32 }
     }
 
-    /// Write audio frames to be input by the host. Returns an Error when no
-    /// input stream has been configured.
-    pub fn write(&self, data: &[u8]) -> Result<usize> {
-        if let Some(ref input) = self.input {
-            input.endpoint.write(data).map_err(Error::UsbError)
-        } else {
-            Err(Error::StreamNotInitialized)
-        }
+    fn max_size(&self) -> u8 {
+        self.formats.iter().fold(self.format.size(), |size, format| size.max(format.size()))
     }
 
-    /// Get current Alternate Setting of the input stream. Returns an error if
-    /// the stream is not configured.
-    pub fn input_alt_setting(&self) -> Result<u8> {
-        self.input
-            .as_ref()
-            .ok_or(Error::StreamNotInitialized)
-            .map(|si| si.alt_setting)
+    // format i of the stream, the primary one being 0
+    fn format_at(&self, i: usize) -> Format {
+        if i == 0 { self.format } else { self.formats[i - 1] }
     }
 
-    /// Get current Alternate Setting of the output stream. Returns an error if
-    /// the stream is not configured.
-    pub fn output_alt_setting(&self) -> Result<u8> {
-        self.output
-            .as_ref()
-            .ok_or(Error::StreamNotInitialized)
-            .map(|si| si.alt_setting)
+    // (bSubslotSize, bBitResolution) of format i
+    fn slot(&self, i: usize) -> (u8, u8) {
+        if i == 0 {
+            (self.format.size(), self.resolution)
+        } else {
+            (self.formats[i - 1].size(), self.formats[i - 1].res())
+        }
     }
 
-}
+    /// The first format offered at alternate setting `alt`, with the combined
+    /// bmFormats and the subslot of that alternate setting
+    fn alt_format(&self, alt: u8) -> Option<(Format, u32, (u8, u8))> {
+        let count = self.formats.len() + 1;
+        let mut n = 0;
 
-impl<B: UsbBus, const R: u16> UsbClass<B> for AudioClass<'_, B, R> {
+        for i in 0..count {
+            let slot = self.slot(i);
 
-    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+            // formats sharing a subslot were offered at an earlier alt
+            if (0..i).any(|j| self.slot(j) == slot) {
+                continue;
+            }
 
-        // PREAMBLE CALCULATIONS
-        let n_interfaces: u8 =
-            if let Some(ref input) = self.input { 1 } else { 0 }
-            + if let Some(ref output) = self.output { 1 } else { 0 };
+            n += 1;
 
-        let total_length: [u8; 2] =
-            ((9 + 8 + (29 * n_interfaces)) as u16).to_be_bytes();
+            if n == alt {
+                let bm_formats = (i..count)
+                    .filter(|&k| self.slot(k) == slot)
+                    .fold(0, |bm, k| bm | self.format_at(k).bm_format());
 
-        // INTERFACE ASSOCIATION DESCRIPTOR
-        writer.write(0x0B, &[
-            0x00, // first interface
-            n_interfaces + 1, // number of interfaces
-            AUDIO_FUNCTION,
-            FUNCTION_SUBCLASS_UNDEFINED,
-            AF_VERSION_02_00,
-            0x00,
-        ]).unwrap();
+                return Some((self.format_at(i), bm_formats, slot));
+            }
+        }
 
-        // BASE INTERFACE DESCRIPTOR
-        writer.interface(self.control_interface, AUDIO, AUDIOCONTROL, IP_VERSION_02_00).unwrap();
+        None
+    }
 
-        // AUDIO CONTROL HEADER
-        let ac_header: [u8; 7] = [
-            HEADER,
-            0x00, // bcdADC 2.00 as big-endian BCD
-            0x02,
-            0x00, // bCategory (none)
-            total_length[1],
-            total_length[0],
-            0x00, // bmControls (none)
-        ];
+    fn alt_settings(&self) -> u8 {
+        (1..=7).take_while(|&alt| self.alt_format(alt).is_some()).count() as u8
+    }
 
-        writer.write(CS_INTERFACE, &ac_header).unwrap();
+}
 
-        // CLOCK SOURCE DESCRIPTOR
-        writer.write(CS_INTERFACE, &[
-            0x0A, // CLOCK_SOURCE subtype
-            ID_CLOCK_SRC,
-            0b00000001, // internal fixed clock
-            0b00000001, // bmControls: clock frequency read only
-            0x00, // assoc terminal (none)
-            0x00, // string index (none)
-        ]).unwrap();
 
-        // AUDIO CONTROL INTERFACE DESCRIPTORS
-        if let Some(ref input) = self.input {
-            input.input_ac_descriptor(writer).unwrap();
-        }
 
-        if let Some(ref output) = self.output {
-            output.output_ac_descriptor(writer).unwrap();
-        }
+/// ENDPOINT ATTRIBUTES
+/// The attributes of an isochronous audio data endpoint. The same value sizes
+/// the endpoint allocation and fills the standard and class-specific endpoint
+/// descriptors, so they cannot disagree. The transfer type is always
+/// isochronous for audio data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EndpointAttributes {
+    pub sync: IsochronousSynchronizationType,
+    pub usage: IsochronousUsageType,
+    /// Require packets of exactly wMaxPacketSize bytes, bit 7 of the class
+    /// specific endpoint's bmAttributes
+    pub max_packets_only: bool,
+}
 
-        // TERMINAL ENDPOINT DESCRIPTORS
-        if let Some(ref input) = self.input {
-            input.input_as_ep_descriptor(writer).unwrap();
-        }
+impl EndpointAttributes {
 
-        if let Some(ref output) = self.output {
-            output.output_as_ep_descriptor(writer).unwrap();
+    fn ep_type(&self) -> EndpointType {
+        EndpointType::Isochronous {
+            synchronization: self.sync,
+            usage: self.usage,
         }
+    }
 
-        Ok(())
-
+    /// bmAttributes of the standard endpoint descriptor
+    pub fn bm_attributes(&self) -> u8 {
+        self.ep_type().to_bm_attributes()
     }
 
-    fn control_out(&mut self, xfer: ControlOut<B>) {
+    /// bmAttributes of the class-specific AS isochronous endpoint descriptor
+    pub fn cs_bm_attributes(&self) -> u8 {
+        if self.max_packets_only { 0b10000000 } else { 0b00000000 }
+    }
 
-        let req = xfer.request();
+}
 
-        if (
-            req.request_type == RequestType::Standard
-            && req.recipient == Recipient::Interface
-            && req.request == Request::SET_INTERFACE
-        ) {
 
-            let interface = req.index as u8;
-            let alt_setting = req.value as u8;
 
-            if let Some(input) = self.input.as_mut() {
-                if interface == input.interface.into() {
-                    input.alt_setting = alt_setting;
-                    xfer.accept().ok();
-                    return;
-                }
-            }
+/// BUS SPEED
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UsbSpeed {
+    Full,
+    High,
+}
 
-            if let Some(output) = self.output.as_mut() {
-                if interface == output.interface.into() {
-                    output.alt_setting = alt_setting;
-                    xfer.accept().ok();
-                    return;
-                }
-            }
+impl UsbSpeed {
 
+    // isochronous bytes per frame (full speed) or microframe (high speed)
+    const fn iso_budget(&self) -> u16 {
+        match self {
+            UsbSpeed::Full => 1023,
+            UsbSpeed::High => 1024,
         }
+    }
 
+    // frames (full speed) or microframes (high speed) per second
+    const fn frames_per_second(&self) -> u64 {
+        match self {
+            UsbSpeed::Full => 1000,
+            UsbSpeed::High => 8000,
+        }
     }
 
-    fn control_in(&mut self, xfer: ControlIn<B>) {
+}
 
-        let req = xfer.request();
 
-        if (
-            req.request_type == RequestType::Standard
-            && req.recipient == Recipient::Interface
-            && req.request == Request::GET_INTERFACE
-            && req.length == 1
-        ) {
-            let interface = req.index as u8;
 
-            if let Some(input) = self.input.as_ref() {
-                if interface == input.interface.into() {
-                    xfer.accept_with(&[input.alt_setting]).ok();
-                    return;
+/// COPY PROTECTION
+/// Copy Protection Level (CPL) of the output terminal's copy protect control,
+/// as defined by IEC 958 / IEC 61937
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CopyProtect {
+    /// Copying is permitted without restriction
+    Cpl0 = 0x00,
+    /// One generation of copies may be made
+    Cpl1 = 0x01,
+    /// Copying is not permitted
+    Cpl2 = 0x02,
+}
+
+impl CopyProtect {
+
+    fn from_level(level: u8) -> Option<CopyProtect> {
+        match level {
+            0x00 => Some(CopyProtect::Cpl0),
+            0x01 => Some(CopyProtect::Cpl1),
+            0x02 => Some(CopyProtect::Cpl2),
+            _ => None,
+        }
+    }
+
+}
+
+
+
+/// RATE POLICY
+/// How the clock answers a SET_CUR of its sampling frequency to a rate it does
+/// not support
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RatePolicy {
+    /// STALL the request, leaving the rate unchanged. This is the behavior the
+    /// specification requires for a value outside the advertised range.
+    Strict,
+    /// Accept the request and clamp to the nearest supported rate, which the
+    /// next GET_CUR reports. Not spec-compliant, but tolerated by hosts that
+    /// re-read the rate after setting it.
+    Clamp,
+}
+
+
+
+/// INTERFACE ORDER
+/// Order in which the configuration descriptor emits the function's interfaces
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InterfaceOrder {
+    /// The AudioControl interface followed by the streaming interfaces, as
+    /// the specification lays out the audio function
+    ControlFirst,
+    /// The streaming interfaces followed by the AudioControl interface. This
+    /// is non-standard and only meant to work around a host that mishandles
+    /// the standard order; compliant hosts may reject it. Interface numbers
+    /// are unchanged, only the descriptors move.
+    StreamingFirst,
+}
+
+
+
+/// CHANNEL CLUSTER
+/// The channels reported by a terminal's cluster control
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cluster {
+    /// Number of logical channels
+    pub n_channels: u8,
+    /// Spatial location of the channels (bmChannelConfig), 0 if unspecified
+    pub channel_config: u32,
+}
+
+impl Cluster {
+
+    fn descriptor(&self) -> [u8; 6] {
+        let config: [u8; 4] = self.channel_config.to_le_bytes();
+
+        [
+            self.n_channels, // bNrChannels
+            config[0], // bmChannelConfig
+            config[1],
+            config[2],
+            config[3],
+            0x00, // string index (none)
+        ]
+    }
+
+}
+
+
+
+/// FEATURE UNIT
+/// Master channel mute and volume controls, and optionally per channel mute
+/// and volume, each present only if enabled
+struct FeatureUnit {
+    mute: Option<bool>,
+    volume: Option<i16>,
+    // bit n - 1 set when logical channel n is muted
+    channel_mute: Option<u32>,
+    // entry n - 1 holds the volume of logical channel n
+    channel_volume: Option<[i16; FeatureUnit::MAX_CHANNELS as usize]>,
+    n_channels: u8,
+}
+
+impl FeatureUnit {
+
+    // per channel controls are limited to the channels the bitmap holds
+    const MAX_CHANNELS: u8 = 32;
+
+    fn descriptor(&self, writer: &mut DescriptorWriter, source: u8) -> usb_device::Result<()> {
+
+        // mute and volume: host programmable, advertised independently
+        let controls: u32 =
+            if self.mute.is_some() { 0b0011 } else { 0b0000 }
+            | if self.volume.is_some() { 0b1100 } else { 0b0000 };
+
+        let channel_controls: u32 =
+            if self.channel_mute.is_some() { 0b0011 } else { 0b0000 }
+            | if self.channel_volume.is_some() { 0b1100 } else { 0b0000 };
+
+        let controls: [u8; 4] = controls.to_le_bytes();
+        let channel_controls: [u8; 4] = channel_controls.to_le_bytes();
+        let n_channels = self.n_channels as usize;
+
+        writer.write_with(CS_INTERFACE, |buf| {
+            let len = 8 + 4 * n_channels;
+
+            if buf.len() < len {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            buf[..3].copy_from_slice(&[
+                FEATURE_UNIT,
+                ID_FEATURE_UNIT, // unit ID
+                source, // source ID
+            ]);
+
+            // bmaControls (master channel)
+            buf[3..7].copy_from_slice(&controls);
+
+            // bmaControls (logical channels)
+            for (i, channel) in buf[7..len - 1].chunks_exact_mut(4).enumerate() {
+                if i < Self::MAX_CHANNELS as usize {
+                    channel.copy_from_slice(&channel_controls);
+                } else {
+                    channel.fill(0x00);
+                }
+            }
+
+            buf[len - 1] = 0x00; // string index (none)
+
+            Ok(len)
+        })?;
+
+        Ok(())
+
+    }
+
+    // the index of a logical channel that has per channel controls
+    fn channel_index(&self, channel: u8) -> Option<usize> {
+        if (1..=self.n_channels.min(Self::MAX_CHANNELS)).contains(&channel) {
+            Some(channel as usize - 1)
+        } else {
+            None
+        }
+    }
+
+    // the bitmap bit of a logical channel with a per channel mute control
+    fn channel_bit(&self, channel: u8) -> Option<u32> {
+        self.channel_mute.and(self.channel_index(channel)).map(|index| 1 << index)
+    }
+
+    fn channel_volume(&self, channel: u8) -> Option<i16> {
+        Some(self.channel_volume?[self.channel_index(channel)?])
+    }
+
+    fn volume_range<B: UsbBus>(xfer: ControlIn<B>) -> Option<ControlKind> {
+        let min: [u8; 2] = VOLUME_MIN.to_le_bytes();
+        let max: [u8; 2] = VOLUME_MAX.to_le_bytes();
+        let res: [u8; 2] = VOLUME_RES.to_le_bytes();
+
+        accept_truncated(xfer, &[
+            0x01, 0x00, // subranges
+            min[0], min[1], // min
+            max[0], max[1], // max
+            res[0], res[1], // res
+        ]).ok();
+        Some(ControlKind::Volume)
+    }
+
+    // a channel is muted by its own control or by the master mute
+    fn channel_muted(&self, channel: u8) -> Option<bool> {
+        let bit = self.channel_bit(channel)?;
+        let channel_mute = self.channel_mute? & bit != 0;
+
+        Some(self.mute.unwrap_or(false) || channel_mute)
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+        let channel = req.value as u8;
+
+        // channels beyond the unit's are left unanswered, stalling them
+        if channel != 0x00 {
+            return match ((req.value >> 8) as u8, req.request, self.channel_bit(channel), self.channel_volume(channel)) {
+                (MUTE_CONTROL, CUR, Some(bit), _) => {
+                    let muted = self.channel_mute.unwrap_or(0) & bit != 0;
+                    accept_truncated(xfer, &[muted as u8]).ok();
+                    Some(ControlKind::Mute)
+                }
+                (VOLUME_CONTROL, CUR, _, Some(volume)) => {
+                    accept_truncated(xfer, &volume.to_le_bytes()).ok();
+                    Some(ControlKind::Volume)
+                }
+                (VOLUME_CONTROL, RANGE, _, Some(_)) => Self::volume_range(xfer),
+                _ => None,
+            };
+        }
+
+        match ((req.value >> 8) as u8, req.request, self.mute, self.volume) {
+
+            (MUTE_CONTROL, CUR, Some(mute), _) => {
+                accept_truncated(xfer, &[mute as u8]).ok();
+                Some(ControlKind::Mute)
+            }
+
+            // mute is a boolean control with no range
+            (MUTE_CONTROL, RANGE, Some(_), _) => {
+                xfer.reject().ok();
+                None
+            }
+
+            (VOLUME_CONTROL, CUR, _, Some(volume)) => {
+                accept_truncated(xfer, &volume.to_le_bytes()).ok();
+                Some(ControlKind::Volume)
+            }
+
+            (VOLUME_CONTROL, RANGE, _, Some(_)) => Self::volume_range(xfer),
+
+            _ => None,
+
+        }
+
+    }
+
+    fn control_out<B: UsbBus>(&mut self, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+        let channel = req.value as u8;
+
+        if req.request != CUR {
+            return None;
+        }
+
+        if channel != 0x00 {
+            return match ((req.value >> 8) as u8, xfer.data(), self.channel_bit(channel), self.channel_index(channel)) {
+                (MUTE_CONTROL, [mute, ..], Some(bit), _) => {
+                    let channel_mute = self.channel_mute.unwrap_or(0);
+
+                    self.channel_mute = Some(if *mute != 0 { channel_mute | bit } else { channel_mute & !bit });
+                    xfer.accept().ok();
+                    Some(ControlKind::Mute)
+                }
+                (VOLUME_CONTROL, [b0, b1, ..], _, Some(index)) if self.channel_volume.is_some() => {
+                    if let Some(ref mut volumes) = self.channel_volume {
+                        volumes[index] = i16::from_le_bytes([*b0, *b1]).clamp(VOLUME_MIN, VOLUME_MAX);
+                    }
+                    xfer.accept().ok();
+                    Some(ControlKind::Volume)
+                }
+                _ => None,
+            };
+        }
+
+        match ((req.value >> 8) as u8, xfer.data()) {
+
+            (MUTE_CONTROL, [mute, ..]) if self.mute.is_some() => {
+                self.mute = Some(*mute != 0);
+                xfer.accept().ok();
+                Some(ControlKind::Mute)
+            }
+
+            (VOLUME_CONTROL, [b0, b1, ..]) if self.volume.is_some() => {
+                self.volume = Some(i16::from_le_bytes([*b0, *b1]).clamp(VOLUME_MIN, VOLUME_MAX));
+                xfer.accept().ok();
+                Some(ControlKind::Volume)
+            }
+
+            _ => None,
+
+        }
+
+    }
+
+}
+
+
+
+// the optional units on one stream's path
+#[derive(Clone, Copy)]
+struct Units<'u> {
+    feature: Option<&'u FeatureUnit>,
+    effect: Option<&'u EffectUnit>,
+}
+
+
+
+// an input terminal a selector or mixer unit adds to the input path, clocked
+// and clustered as the input stream's own
+fn extra_input_terminal(writer: &mut DescriptorWriter, id: u8, term_type: TerminalType, clock_id: u8, cluster: Cluster) -> usb_device::Result<()> {
+
+    let input_type: [u8; 2] = term_type.as_bytes();
+    let channel_config: [u8; 4] = cluster.channel_config.to_le_bytes();
+
+    writer.write(CS_INTERFACE, &[
+        INPUT_TERMINAL,
+        id, // terminal ID
+        input_type[0], // terminal type
+        input_type[1],
+        0x00, // associated terminal (none)
+        clock_id, // clock source ID
+        cluster.n_channels, // logical channels
+        channel_config[0], // spacial description config
+        channel_config[1],
+        channel_config[2],
+        channel_config[3],
+        0x00, // channel names string index (none)
+        0x00, 0x00, // bmControls (none)
+        0x00, // terminal desc string index (none)
+    ])
+
+}
+
+
+
+/// SELECTOR UNIT
+/// Chooses the source of the input stream between its input terminal (pin 1)
+/// and further input terminals of the given types (pins 2 and on), host
+/// programmable
+struct SelectorUnit<'a> {
+    terminals: &'a [TerminalType],
+    selected: u8,
+}
+
+impl SelectorUnit<'_> {
+
+    fn n_pins(&self) -> u8 {
+        1 + self.terminals.len() as u8
+    }
+
+    // input terminal entity of input pin `pin`, counted from 1
+    fn source_id(pin: u8) -> u8 {
+        if pin <= 1 { ID_INPUT_TERMINAL } else { ID_SELECTABLE_INPUT_TERMINAL[pin as usize - 2] }
+    }
+
+    // the selectable input terminals, then the unit itself
+    fn descriptor(&self, writer: &mut DescriptorWriter, clock_id: u8, cluster: Cluster) -> usb_device::Result<()> {
+
+        for (&term_type, id) in self.terminals.iter().zip(ID_SELECTABLE_INPUT_TERMINAL) {
+            extra_input_terminal(writer, id, term_type, clock_id, cluster)?;
+        }
+
+        let n_pins = self.n_pins() as usize;
+        let mut descriptor = [0u8; 5 + MAX_SELECTABLE_INPUTS + 1];
+
+        descriptor[..3].copy_from_slice(&[
+            SELECTOR_UNIT,
+            ID_SELECTOR_UNIT, // unit ID
+            self.n_pins(), // number of input pins
+        ]);
+
+        // input terminal ID of each input pin
+        for (pin, id) in descriptor[3..3 + n_pins].iter_mut().enumerate() {
+            *id = Self::source_id(pin as u8 + 1);
+        }
+
+        descriptor[3 + n_pins] = 0b00000011; // bmControls: selector host programmable
+        descriptor[4 + n_pins] = 0x00; // string index (none)
+
+        writer.write(CS_INTERFACE, &descriptor[..5 + n_pins])
+
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        if (req.value >> 8) as u8 != SU_SELECTOR_CONTROL || req.request != CUR {
+            return None;
+        }
+
+        accept_truncated(xfer, &[self.selected]).ok();
+        Some(ControlKind::InputSelector)
+
+    }
+
+    fn control_out<B: UsbBus>(&mut self, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        if (req.value >> 8) as u8 != SU_SELECTOR_CONTROL || req.request != CUR {
+            return None;
+        }
+
+        match xfer.data().first().copied() {
+            Some(pin) if (1..=self.n_pins()).contains(&pin) => {
+                self.selected = pin;
+                xfer.accept().ok();
+                Some(ControlKind::InputSelector)
+            }
+            _ => {
+                xfer.reject().ok();
+                None
+            }
+        }
+
+    }
+
+}
+
+
+
+/// MIXER UNIT
+/// Mixes the input path (pin 1) with further input terminals of the given
+/// types (pins 2 and on) into the input stream's cluster. Every input channel
+/// reaches every output channel through a host programmable level in 1/256
+/// dB, addressed by its Mixer Control Number: input channel major, counted
+/// from 0.
+struct MixerUnit<'a> {
+    terminals: &'a [TerminalType],
+    // channels carried by each input pin
+    pin_channels: u8,
+    n_outputs: u8,
+    levels: [i16; MAX_MIXER_CONTROLS],
+}
+
+impl<'a> MixerUnit<'a> {
+
+    // the first input's channels pass straight through, the rest are silent
+    fn new(terminals: &'a [TerminalType], pin_channels: u8, n_outputs: u8) -> MixerUnit<'a> {
+        let mut levels = [MIXER_SILENT; MAX_MIXER_CONTROLS];
+
+        for channel in 0..pin_channels.min(n_outputs) as usize {
+            levels[channel * n_outputs as usize + channel] = 0;
+        }
+
+        MixerUnit {
+            terminals,
+            pin_channels,
+            n_outputs,
+            levels,
+        }
+    }
+
+    fn n_pins(&self) -> u8 {
+        1 + self.terminals.len() as u8
+    }
+
+    fn n_controls(&self) -> usize {
+        self.n_pins() as usize * self.pin_channels as usize * self.n_outputs as usize
+    }
+
+    // the mixed in input terminals, then the unit itself with its first pin
+    // on `source`
+    fn descriptor(&self, writer: &mut DescriptorWriter, source: u8, clock_id: u8, cluster: Cluster, output_config: u32) -> usb_device::Result<()> {
+
+        for (&term_type, id) in self.terminals.iter().zip(ID_MIXER_INPUT_TERMINAL) {
+            extra_input_terminal(writer, id, term_type, clock_id, cluster)?;
+        }
+
+        let n_pins = self.n_pins() as usize;
+        let n_controls = self.n_controls();
+        let channel_config: [u8; 4] = output_config.to_le_bytes();
+
+        writer.write_with(CS_INTERFACE, |buf| {
+            let controls_length = n_controls.div_ceil(8);
+            let len = 11 + n_pins + controls_length;
+
+            if buf.len() < len {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            buf[..3].copy_from_slice(&[
+                MIXER_UNIT,
+                ID_MIXER_UNIT, // unit ID
+                self.n_pins(), // number of input pins
+            ]);
+
+            // source ID of each input pin
+            buf[3] = source;
+            buf[4..3 + n_pins].copy_from_slice(&ID_MIXER_INPUT_TERMINAL[..n_pins - 1]);
+
+            let cluster_start = 3 + n_pins;
+            buf[cluster_start..cluster_start + 6].copy_from_slice(&[
+                self.n_outputs, // bNrChannels
+                channel_config[0], // bmChannelConfig
+                channel_config[1],
+                channel_config[2],
+                channel_config[3],
+                0x00, // channel names string index (none)
+            ]);
+
+            // bmMixerControls: every level programmable, MSB first
+            let controls = &mut buf[cluster_start + 6..cluster_start + 6 + controls_length];
+            controls.fill(0x00);
+            for control in 0..n_controls {
+                controls[control / 8] |= 0x80 >> (control % 8);
+            }
+
+            buf[len - 2] = 0x00; // bmControls (none)
+            buf[len - 1] = 0x00; // string index (none)
+
+            Ok(len)
+        })?;
+
+        Ok(())
+
+    }
+
+    // the level of input channel `input` to output channel `output`, both
+    // logical channel numbers counted from 1
+    fn level(&self, input: u8, output: u8) -> Option<i16> {
+        if input == 0 || output == 0 || output > self.n_outputs {
+            return None;
+        }
+
+        let control = (input as usize - 1) * self.n_outputs as usize + output as usize - 1;
+
+        self.levels[..self.n_controls()].get(control).copied()
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+        let control = req.value as u8 as usize;
+
+        if (req.value >> 8) as u8 != MU_MIXER_CONTROL || control >= self.n_controls() {
+            return None;
+        }
+
+        match req.request {
+
+            CUR => {
+                accept_truncated(xfer, &self.levels[control].to_le_bytes()).ok();
+                Some(ControlKind::Mixer)
+            }
+
+            // levels share the volume control's range
+            RANGE => FeatureUnit::volume_range(xfer).map(|_| ControlKind::Mixer),
+
+            _ => None,
+
+        }
+
+    }
+
+    fn control_out<B: UsbBus>(&mut self, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+        let control = req.value as u8 as usize;
+
+        if (req.value >> 8) as u8 != MU_MIXER_CONTROL || req.request != CUR || control >= self.n_controls() {
+            return None;
+        }
+
+        match xfer.data() {
+            [b0, b1, ..] => {
+                let level = i16::from_le_bytes([*b0, *b1]);
+
+                self.levels[control] = if level == MIXER_SILENT { level } else { level.clamp(VOLUME_MIN, VOLUME_MAX) };
+                xfer.accept().ok();
+                Some(ControlKind::Mixer)
+            }
+            _ => {
+                xfer.reject().ok();
+                None
+            }
+        }
+
+    }
+
+}
+
+
+
+/// CLOCK MULTIPLIER
+/// A clock derived from the first clock source by a numerator / denominator
+/// ratio, both read only controls answered from firmware-provided values
+struct ClockMultiplier {
+    numerator: u16,
+    denominator: u16,
+}
+
+impl ClockMultiplier {
+
+    fn descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+        writer.write(CS_INTERFACE, &[
+            CLOCK_MULTIPLIER,
+            ID_CLOCK_MULTIPLIER, // clock ID
+            ID_CLOCK_SRC, // clock source ID
+            0b00000101, // bmControls: numerator and denominator read only
+            0x00, // string index (none)
+        ])?;
+
+        Ok(())
+
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        let value = match ((req.value >> 8) as u8, req.request) {
+            (CM_NUMERATOR_CONTROL, CUR) => self.numerator,
+            (CM_DENOMINATOR_CONTROL, CUR) => self.denominator,
+            _ => return None,
+        };
+
+        accept_truncated(xfer, &value.to_le_bytes()).ok();
+        Some(ControlKind::ClockMultiplier)
+
+    }
+
+}
+
+
+
+/// CLOCK SELECTOR
+/// Chooses the clock of the streams between the first clock source (pin 1)
+/// and the selectable ones (pins 2 and on), host programmable
+struct ClockSelector {
+    n_pins: u8,
+    selected: u8,
+}
+
+impl ClockSelector {
+
+    // clock source entity of input pin `pin`, counted from 1
+    fn source_id(pin: u8) -> u8 {
+        if pin <= 1 { ID_CLOCK_SRC } else { ID_SELECTABLE_CLOCK_SRC[pin as usize - 2] }
+    }
+
+    fn descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+        let n_pins = self.n_pins as usize;
+        let mut descriptor = [0u8; 5 + MAX_SELECTABLE_CLOCKS + 1];
+
+        descriptor[..3].copy_from_slice(&[
+            CLOCK_SELECTOR,
+            ID_CLOCK_SELECTOR, // clock ID
+            self.n_pins, // number of input pins
+        ]);
+
+        // clock source ID of each input pin
+        for (pin, id) in descriptor[3..3 + n_pins].iter_mut().enumerate() {
+            *id = Self::source_id(pin as u8 + 1);
+        }
+
+        descriptor[3 + n_pins] = 0b00000011; // bmControls: clock selector host programmable
+        descriptor[4 + n_pins] = 0x00; // string index (none)
+
+        writer.write(CS_INTERFACE, &descriptor[..5 + n_pins])
+
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        if (req.value >> 8) as u8 != CX_CLOCK_SELECTOR_CONTROL || req.request != CUR {
+            return None;
+        }
+
+        accept_truncated(xfer, &[self.selected]).ok();
+        Some(ControlKind::ClockSelector)
+
+    }
+
+    fn control_out<B: UsbBus>(&mut self, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        if (req.value >> 8) as u8 != CX_CLOCK_SELECTOR_CONTROL || req.request != CUR {
+            return None;
+        }
+
+        match xfer.data().first().copied() {
+            Some(pin) if (1..=self.n_pins).contains(&pin) => {
+                self.selected = pin;
+                xfer.accept().ok();
+                Some(ControlKind::ClockSelector)
+            }
+            _ => {
+                xfer.reject().ok();
+                None
+            }
+        }
+
+    }
+
+}
+
+
+
+/// CLOCK SOURCE
+struct Clock<'a> {
+    id: u8,
+    rate: u32,
+    source: ClockSource<'a>,
+    name: Option<StringIndex>,
+    valid: bool,
+}
+
+impl<'a> Clock<'a> {
+
+    fn new(id: u8, rate: u32, source: ClockSource<'a>, name: Option<StringIndex>) -> Clock<'a> {
+        Clock {
+            id,
+            rate,
+            source,
+            name,
+            // invalid until firmware reports the clock locked
+            valid: false,
+        }
+    }
+
+    fn get_string(&self, index: StringIndex) -> Option<&str> {
+        if self.name == Some(index) { self.source.name } else { None }
+    }
+
+    // the supported rates, or just the configured one when none were given
+    fn supported_rates(&self) -> &[u32] {
+        if self.source.rates.is_empty() { core::slice::from_ref(&self.rate) } else { self.source.rates }
+    }
+
+    fn nearest_rate(&self, requested: SampleRate) -> u32 {
+        let hz = requested.hz();
+
+        self.supported_rates()
+            .iter()
+            .copied()
+            .min_by_key(|rate| rate.abs_diff(hz))
+            .unwrap_or(self.rate)
+    }
+
+    fn descriptor(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+        // clock validity read only, clock frequency host programmable when
+        // there is a choice of rates, otherwise read only or absent
+        let controls: u8 = 0b00000100
+            | match (self.source.frequency_control, self.supported_rates().len()) {
+                (false, _) => 0b00,
+                (true, 1) => 0b01,
+                (true, _) => 0b11,
+            };
+
+        // clock type, optionally synchronized to SOF
+        let attributes: u8 = self.source.clock_type as u8
+            | if self.source.sof_sync { 0b100 } else { 0b000 };
+
+        writer.write(CS_INTERFACE, &[
+            CLOCK_SOURCE,
+            self.id,
+            attributes, // bmAttributes
+            controls, // bmControls
+            0x00, // assoc terminal (none)
+            self.name.map_or(0x00, u8::from), // string index
+        ])?;
+
+        Ok(())
+
+    }
+
+    fn control_in<B: UsbBus>(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+        let n_ranges: [u8; 2] = (self.supported_rates().len() as u16).to_le_bytes();
+
+        match ((req.value >> 8) as u8, req.request) {
+
+            (CS_SAM_FREQ_CONTROL, _) if !self.source.frequency_control => None,
+
+            // the full parameter block every time, truncated to wLength, so
+            // hosts reading just the subrange count first get the same bytes
+            (CS_SAM_FREQ_CONTROL, RANGE) => {
+                let rates = self.supported_rates();
+                let length = req.length as usize;
+
+                xfer.accept(|buf| {
+                    let len = 2 + 12 * rates.len();
+
+                    if buf.len() < len {
+                        return Err(UsbError::BufferOverflow);
+                    }
+
+                    buf[..2].copy_from_slice(&n_ranges); // subranges
+
+                    // one subrange per discrete rate
+                    for (range, rate) in buf[2..len].chunks_exact_mut(12).zip(rates) {
+                        range[0..4].copy_from_slice(&rate.to_le_bytes()); // min
+                        range[4..8].copy_from_slice(&rate.to_le_bytes()); // max
+                        range[8..12].copy_from_slice(&1u32.to_le_bytes()); // res
+                    }
+
+                    Ok(len.min(length))
+                }).ok();
+                Some(ControlKind::ClockRange)
+            }
+
+            (CS_SAM_FREQ_CONTROL, CUR) => {
+                accept_truncated(xfer, &SampleRate::from_hz(self.rate).to_bytes()).ok();
+                Some(ControlKind::ClockCur)
+            }
+
+            (CS_CLOCK_VALID_CONTROL, CUR) => {
+                accept_truncated(xfer, &[self.valid as u8]).ok();
+                Some(ControlKind::ClockValid)
+            }
+
+            _ => None,
+
+        }
+
+    }
+
+    fn control_out<B: UsbBus>(&mut self, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        if !self.source.frequency_control || (req.value >> 8) as u8 != CS_SAM_FREQ_CONTROL || req.request != CUR {
+            return None;
+        }
+
+        let requested = match xfer.data() {
+            [b0, b1, b2, b3, ..] => SampleRate::from_bytes([*b0, *b1, *b2, *b3]),
+            _ => {
+                xfer.reject().ok();
+                return None;
+            }
+        };
+
+        if self.set_rate(requested) {
+            xfer.accept().ok();
+            Some(ControlKind::ClockCur)
+        } else {
+            xfer.reject().ok();
+            None
+        }
+
+    }
+
+    // take the requested rate, or the nearest supported one when the policy
+    // allows, returning false when the request is refused
+    fn set_rate(&mut self, requested: SampleRate) -> bool {
+        let nearest = self.nearest_rate(requested);
+
+        if nearest == requested.hz() || self.source.rate_policy == RatePolicy::Clamp {
+            self.rate = nearest;
+            true
+        } else {
+            false
+        }
+    }
+
+}
+
+
+
+/// AUDIO STREAM
+pub struct AudioStream<'a, B: UsbBus, D: EndpointDirection> {
+    stream_config: StreamConfig<'a>,
+    interface: InterfaceNumber,
+    endpoint: Endpoint<'a, B, D>,
+    alt_setting: AtomicU8,
+    clock_id: u8,
+    // the rate of the stream's clock, kept in step by the control half as
+    // the host changes it
+    rate: AtomicU32,
+    feedback: Option<Endpoint<'a, B, In>>,
+    last_feedback: AtomicU32,
+    in_flight: AtomicBool,
+    received: AtomicBool,
+    attributes: EndpointAttributes,
+    speed: UsbSpeed,
+    terminal_name: Option<StringIndex>,
+    channel_names: Option<StringIndex>,
+}
+
+impl<B: UsbBus, D: EndpointDirection> AudioStream<'_, B, D> {
+
+    fn string_index(&self, index: Option<StringIndex>) -> u8 {
+        index.map_or(0x00, u8::from)
+    }
+
+    fn get_string(&self, index: StringIndex) -> Option<&str> {
+        if self.terminal_name == Some(index) {
+            self.stream_config.terminal_name
+        } else if let Some(first) = self.channel_names {
+            // the channel names follow the first index consecutively
+            let channel = u8::from(index).wrapping_sub(first.into());
+
+            if channel < self.stream_config.n_channels {
+                self.stream_config.channel_names.get(channel as usize).copied()
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    fn input_ac_descriptor(&self, writer: &mut DescriptorWriter, sink: Option<TerminalType>, selector: Option<&SelectorUnit>, mixer: Option<&MixerUnit>, units: Units, assoc: u8) -> usb_device::Result<()> {
+
+        let input_type: [u8; 2] = self.stream_config.term_type.as_bytes();
+        let output_type: [u8; 2] = TerminalType::UsbStreaming.as_bytes();
+
+        // the physical terminal may carry more channels than the USB stream
+        let cluster: Cluster = self.stream_config.terminal_cluster.unwrap_or(Cluster {
+            n_channels: self.stream_config.n_channels,
+            channel_config: self.stream_config.channel_config.bits(),
+        });
+        let channel_config: [u8; 4] = cluster.channel_config.to_le_bytes();
+
+        writer.write(CS_INTERFACE, &[
+            INPUT_TERMINAL,
+            ID_INPUT_TERMINAL, // terminal ID
+            input_type[0], // terminal type
+            input_type[1],
+            assoc, // associated terminal
+            self.clock_id, // clock source ID
+            cluster.n_channels, // logical channels
+            channel_config[0], // spacial description config
+            channel_config[1],
+            channel_config[2],
+            channel_config[3],
+            self.string_index(self.channel_names), // channel names string index
+            0x00, 0x00, // bmControls (none)
+            self.string_index(self.terminal_name), // terminal desc string index
+        ])?;
+
+        // a selector picks between the input terminals, before any units
+        let source = match selector {
+            Some(selector) => {
+                selector.descriptor(writer, self.clock_id, cluster)?;
+                ID_SELECTOR_UNIT
+            }
+            None => ID_INPUT_TERMINAL,
+        };
+
+        // a mixer then mixes further input terminals into the stream's cluster
+        let source = match mixer {
+            Some(mixer) => {
+                mixer.descriptor(writer, source, self.clock_id, cluster, self.stream_config.channel_config.bits())?;
+                ID_MIXER_UNIT
+            }
+            None => source,
+        };
+
+        // any units sit between the input terminal and the USB stream
+        let stream_source = self.unit_descriptors(writer, source, units)?;
+
+        writer.write(CS_INTERFACE, &[
+            OUTPUT_TERMINAL,
+            ID_INPUT_STREAMING, // terminal ID
+            output_type[0], // terminal type
+            output_type[1],
+            0x00, // associated terminal (none)
+            stream_source, // source ID
+            self.clock_id, // clock source ID
+            0x00, // bmControls (none)
+            0x00,
+            0x00, // terminal desc string index (none)
+        ])?;
+
+        // additional non-USB sink fed from the same input terminal
+        if let Some(sink) = sink {
+            let sink_type: [u8; 2] = sink.as_bytes();
+
+            writer.write(CS_INTERFACE, &[
+                OUTPUT_TERMINAL,
+                ID_INPUT_SINK, // terminal ID
+                sink_type[0], // terminal type
+                sink_type[1],
+                0x00, // associated terminal (none)
+                ID_INPUT_TERMINAL, // source ID (the above input terminal)
+                self.clock_id, // clock source ID
+                0x00, // bmControls (none)
+                0x00,
+                0x00, // terminal desc string index (none)
+            ])?;
+        }
+
+        Ok(())
+
+    }
+
+    fn output_ac_descriptor(&self, writer: &mut DescriptorWriter, input_controls: u16, terminal_controls: u16, units: Units, assoc: u8) -> usb_device::Result<()> {
+
+        let input_type: [u8; 2] = TerminalType::UsbStreaming.as_bytes();
+        let output_type: [u8; 2] = self.stream_config.term_type.as_bytes();
+        let in_controls: [u8; 2] = input_controls.to_le_bytes();
+        let controls: [u8; 2] = terminal_controls.to_le_bytes();
+        let channel_config: [u8; 4] = self.stream_config.channel_config.bits().to_le_bytes();
+
+        writer.write(CS_INTERFACE, &[
+            INPUT_TERMINAL,
+            ID_OUTPUT_STREAMING, // terminal ID
+            input_type[0], // terminal type
+            input_type[1],
+            0x00, // associated terminal (no assoc)
+            self.clock_id, // clock source ID
+            self.stream_config.n_channels, // logical channels
+            channel_config[0], // spacial description config
+            channel_config[1],
+            channel_config[2],
+            channel_config[3],
+            self.string_index(self.channel_names), // channel names string index
+            in_controls[0], // bmControls
+            in_controls[1],
+            0x00, // terminal desc string index (none)
+        ])?;
+
+        // any units sit between the USB stream and the output terminal
+        let terminal_source = self.unit_descriptors(writer, ID_OUTPUT_STREAMING, units)?;
+
+        writer.write(CS_INTERFACE, &[
+            OUTPUT_TERMINAL,
+            ID_OUTPUT_TERMINAL, // terminal ID
+            output_type[0], // terminal type
+            output_type[1],
+            assoc, // associated terminal
+            terminal_source, // source ID
+            self.clock_id, // clock source ID
+            controls[0], // bmControls
+            controls[1],
+            self.string_index(self.terminal_name), // terminal desc string index
+        ])?;
+
+        Ok(())
+    }
+
+    // write the feature unit then the effect unit, each sourcing from the
+    // entity before it, and return the last entity of the chain. The terminal
+    // after the units takes its bSourceID from the returned ID, so inserting
+    // a unit rewires the path.
+    fn unit_descriptors(&self, writer: &mut DescriptorWriter, source: u8, units: Units) -> usb_device::Result<u8> {
+        let mut source = source;
+
+        if let Some(feature) = units.feature {
+            feature.descriptor(writer, source)?;
+            source = ID_FEATURE_UNIT;
+        }
+
+        if let Some(effect) = units.effect {
+            self.effect_descriptor(writer, source, effect)?;
+            source = ID_EFFECT_UNIT;
+        }
+
+        Ok(source)
+    }
+
+    fn effect_descriptor(&self, writer: &mut DescriptorWriter, source: u8, effect: &EffectUnit) -> usb_device::Result<()> {
+
+        let effect_type: [u8; 2] = (effect.effect_type as u16).to_le_bytes();
+        let controls: [u8; 4] = effect.controls.to_le_bytes();
+        let n_channels = self.stream_config.n_channels as usize;
+
+        writer.write_with(CS_INTERFACE, |buf| {
+            let len = 10 + 4 * n_channels;
+
+            if buf.len() < len {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            buf[..5].copy_from_slice(&[
+                EFFECT_UNIT,
+                ID_EFFECT_UNIT, // unit ID
+                effect_type[0], // effect type
+                effect_type[1],
+                source, // source ID
+            ]);
+
+            // bmaControls (master channel)
+            buf[5..9].copy_from_slice(&controls);
+
+            // bmaControls (logical channels, none)
+            buf[9..len - 1].fill(0x00);
+
+            buf[len - 1] = 0x00; // string index (none)
+
+            Ok(len)
+        })?;
+
+        Ok(())
+
+    }
+
+    fn input_as_ep_descriptor(&self, writer: &mut DescriptorWriter, as_controls: u8) -> usb_device::Result<()> {
+
+        // AUDIO STREAMING DESCRIPTORS
+        // alt 0 has no endpoints so the host can idle the stream
+        writer.interface(self.interface, AUDIO, AUDIOSTREAMING, IP_VERSION_02_00)?;
+
+        // one operational alt setting per offered subslot
+        for alt in 1..=self.stream_config.alt_settings() {
+            let (_, bm_formats, slot) = self.stream_config.alt_format(alt).unwrap();
+            let bm_formats: [u8; 4] = bm_formats.to_le_bytes();
+            let channel_config: [u8; 4] = self.stream_config.channel_config.bits().to_le_bytes();
+
+            writer.write(INTERFACE, &[
+                self.interface.into(),
+                alt, // alternate setting
+                0x01, // n endpoints (1 data endpoint)
+                AUDIO,
+                AUDIOSTREAMING,
+                IP_VERSION_02_00,
+                0x00,
+            ])?;
+
+            writer.write(CS_INTERFACE, &[
+                AS_GENERAL,
+                ID_INPUT_STREAMING, // input interface ID (USB streaming)
+                as_controls, // bmControls
+                0x01, // format type I
+                bm_formats[0], // audio data formats
+                bm_formats[1],
+                bm_formats[2],
+                bm_formats[3],
+                self.stream_config.n_channels,
+                channel_config[0], // spacial location description
+                channel_config[1],
+                channel_config[2],
+                channel_config[3],
+                self.string_index(self.channel_names), // channel names string index
+            ])?;
+
+            writer.write(CS_INTERFACE, &[
+                FORMAT_TYPE,
+                FORMAT_TYPE_I,
+                slot.0, // bSubslotSize
+                slot.1, // bBitResolution
+            ])?;
+
+            // ENDPOINT DESCRIPTORS
+            /*
+            The standard writer endpoint function would count these endpoints against the
+            wrong interface, as the alt settings are written by hand. So, this is done
+            manually with the fields filled from the endpoint and its attributes.
+             */
+            let max_transfer: [u8; 2] = self.endpoint.max_packet_size().to_be_bytes();
+
+            writer.write(0x05, &[
+                self.endpoint.address().into(),
+                self.attributes.bm_attributes(), // bmAttributes
+                max_transfer[1],
+                max_transfer[0],
+                self.endpoint.interval(),
+            ])?;
+
+            writer.write(CS_ENDPOINT, &[
+                EP_GENERAL,
+                self.attributes.cs_bm_attributes(), // bmAttributes
+                0x00, // bmControls
+                0x00, // bLockDelayUnits
+                0x00, 0x00 // wLockDelay
+            ])?;
+        }
+
+        Ok(())
+
+    }
+
+    fn output_as_ep_descriptor(&self, writer: &mut DescriptorWriter, as_controls: u8) -> usb_device::Result<()> {
+
+        // AUDIO STREAMING DESCRIPTORS
+        // alt 0 has no endpoints so the host can idle the stream
+        writer.interface(self.interface, AUDIO, AUDIOSTREAMING, IP_VERSION_02_00)?;
+
+        for alt in 1..=self.stream_config.alt_settings() {
+            let (_, bm_formats, slot) = self.stream_config.alt_format(alt).unwrap();
+            let bm_formats: [u8; 4] = bm_formats.to_le_bytes();
+            let channel_config: [u8; 4] = self.stream_config.channel_config.bits().to_le_bytes();
+
+            writer.write(INTERFACE, &[
+                self.interface.into(),
+                alt, // alternate setting
+                0x01 + self.feedback.is_some() as u8, // n endpoints (data and feedback)
+                AUDIO,
+                AUDIOSTREAMING,
+                IP_VERSION_02_00,
+                0x00,
+            ])?;
+
+            writer.write(CS_INTERFACE, &[
+                AS_GENERAL,
+                ID_OUTPUT_STREAMING,
+                as_controls,
+                0x01,
+                bm_formats[0],
+                bm_formats[1],
+                bm_formats[2],
+                bm_formats[3],
+                self.stream_config.n_channels,
+                channel_config[0],
+                channel_config[1],
+                channel_config[2],
+                channel_config[3],
+                self.string_index(self.channel_names),
+            ])?;
+
+            writer.write(CS_INTERFACE, &[
+                FORMAT_TYPE,
+                FORMAT_TYPE_I,
+                slot.0, // bSubslotSize
+                slot.1, // bBitResolution
+            ])?;
+
+            let max_transfer: [u8; 2] = self.endpoint.max_packet_size().to_be_bytes();
+
+            writer.write(0x05, &[
+                self.endpoint.address().into(),
+                self.attributes.bm_attributes(), // bmAttributes
+                max_transfer[1],
+                max_transfer[0],
+                self.endpoint.interval(),
+            ])?;
+
+            writer.write(CS_ENDPOINT, &[
+                EP_GENERAL,
+                self.attributes.cs_bm_attributes(), // bmAttributes
+                0x00, // bmControls
+                0x00, // bLockDelayUnits
+                0x00, 0x00 // wLockDelay
+            ])?;
+
+            if let Some(ref feedback) = self.feedback {
+                writer.write(0x05, &[
+                    feedback.address().into(),
+                    feedback.ep_type().to_bm_attributes(), // bmAttributes
+                    feedback.max_packet_size() as u8,
+                    0x00,
+                    feedback.interval(),
+                ])?;
+            }
+        }
+
+        Ok(())
+
+    }
+
+    fn control_in(&self, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+        let req = xfer.request();
+
+        match ((req.value >> 8) as u8, req.request) {
+
+            (AS_ACT_ALT_SETTING_CONTROL, CUR) => {
+                accept_truncated(xfer, &[self.alt_setting.load(Ordering::Relaxed)]).ok();
+                Some(ControlKind::AltSettings)
+            }
+
+            (AS_VAL_ALT_SETTINGS_CONTROL, CUR) => {
+                // alt 0 and each operational alt setting
+                let valid: u8 = ((1u16 << (self.stream_config.alt_settings() + 1)) - 1) as u8;
+
+                accept_truncated(xfer, &[
+                    0x01, // bControlSize
+                    valid, // bmValidAltSettings
+                ]).ok();
+                Some(ControlKind::AltSettings)
+            }
+
+            _ => None,
+
+        }
+
+    }
+
+    fn format(&self) -> Option<Format> {
+        self.stream_config
+            .alt_format(self.alt_setting.load(Ordering::Relaxed))
+            .map(|(format, _, _)| format)
+    }
+
+    // nominal bytes per 1 ms frame at the clock's current rate, in the subslot
+    // of the selected alternate setting or of the first while closed
+    fn bytes_per_frame(&self) -> u16 {
+        let frame_size = self.frame_size().unwrap_or(self.stream_config.format.size() as usize * self.stream_config.n_channels as usize);
+
+        ((self.rate.load(Ordering::Relaxed) / 1000) as usize * frame_size) as u16
+    }
+
+    // bytes of one audio frame, a sample of every channel, in the subslot of
+    // the selected alternate setting; None at alternate setting 0
+    fn frame_size(&self) -> Option<usize> {
+        self.stream_config
+            .alt_format(self.alt_setting.load(Ordering::Relaxed))
+            .map(|(_, _, (subslot, _))| subslot as usize * self.stream_config.n_channels as usize)
+    }
+
+    fn write_feedback(&self, value: u32) -> Result<usize> {
+        let feedback = self.feedback.as_ref().ok_or(Error::StreamNotInitialized)?;
+
+        let n = feedback
+            .write(&value.to_le_bytes()[..feedback::packet_size(self.speed) as usize])
+            .map_err(Error::UsbError)?;
+
+        self.last_feedback.store(value, Ordering::Relaxed);
+        Ok(n)
+    }
+
+}
+
+
+
+/// AUDIO CLASS
+///
+/// Control requests handled from `UsbDevice::poll` only update the class state
+/// (alternate settings, clock and terminal controls); they never touch the
+/// isochronous data endpoints. `read()` and `write()` only touch those
+/// endpoints and read no state that the control handlers write. Control and
+/// data calls may therefore be interleaved in any order. A stream changed by
+/// SET_INTERFACE takes effect from the next `read()` or `write()`. When the
+/// two run in different interrupts, use `split()` rather than sharing the
+/// whole class behind a lock.
+pub struct AudioClass<'a, B: UsbBus> {
+    streams: AudioStreams<'a, B>,
+    control: ControlState<'a, B>,
+}
+
+impl<'a, B: UsbBus> AudioClass<'a, B> {
+
+    /// Build the smallest valid configuration, a mono 48 kHz 16 bit
+    /// microphone, as a known-good reference for compliance tools. With N
+    /// the first interface number it is given, the function consists of:
+    ///
+    /// - IAD: first interface N, 2 interfaces, AUDIO_FUNCTION, version 2.00
+    /// - AudioControl interface N, with the AC header: bcdADC 2.00, category
+    ///   Microphone (0x03), wTotalLength 46
+    /// - Clock Source 0x01: internal fixed clock, frequency control read
+    ///   only, validity read only and already reported valid
+    /// - Input Terminal 0x02: Microphone (0x0201), clock 0x01, 1 channel
+    ///   located front center
+    /// - Output Terminal 0x03: USB Streaming (0x0101), source 0x02, clock 0x01
+    /// - AudioStreaming interface N+1, alt 0 without endpoints
+    /// - Alt 1: AS_GENERAL for terminal 0x03, PCM, 1 front center channel;
+    ///   Type I format with a 2 byte subslot and 16 bit resolution; one
+    ///   asynchronous isochronous IN endpoint of 98 bytes, interval 1, used
+    ///   for implicit feedback
+    ///
+    /// Every other option is left at its default. Unlike a class from
+    /// `build()`, the clock needs no `set_clock_valid()` before the host
+    /// streams: an internal clock runs as soon as the device does.
+    pub fn minimal_input(allocator: &'a UsbBusAllocator<B>) -> Result<AudioClass<'a, B>> {
+        let mut class = AudioClassBuilder::new()
+            .profile(AudioProfile::Microphone, 48000)?
+            .build(allocator)?;
+
+        class.set_clock_valid(ID_CLOCK_SRC, true)?;
+        Ok(class)
+    }
+
+    /// Split the class into a control half, which implements `UsbClass` and is
+    /// polled by `UsbDevice`, and a streaming half, which moves the audio data.
+    ///
+    /// The control half owns all control state exclusively. The only state
+    /// shared between the halves is the alternate setting, sample rate, last
+    /// feedback value and input packet completion of each stream, which are
+    /// atomics written by one half and read by the other, so the halves may
+    /// be used from different interrupts without a lock. Both halves access
+    /// the bus only through their own endpoints, relying on the `Sync`
+    /// requirement of `UsbBus`.
+    pub fn split(&mut self) -> (AudioControl<'_, 'a, B>, &AudioStreams<'a, B>) {
+        (
+            AudioControl {
+                streams: &self.streams,
+                control: &mut self.control,
+            },
+            &self.streams,
+        )
+    }
+
+    /// Read audio frames as output by the host. Returns an Error if no output
+    /// stream has been configured, or `StreamIdle` while the host has the
+    /// stream at alternate setting 0 and sends no data.
+    pub fn read(&self, data: &mut [u8]) -> Result<usize> {
+        self.streams.read(data)
+    }
+
+    /// Read audio frames as `read()` does, counting the complete frames of
+    /// the format the host selected. The bytes of a trailing partial frame
+    /// are left in `data` after the complete ones and counted in `leftover`.
+    pub fn read_frame(&self, data: &mut [u8]) -> Result<FrameRead> {
+        self.streams.read_frame(data)
+    }
+
+    /// Write audio frames to be input by the host. Returns an Error when no
+    /// input stream has been configured, or `StreamIdle` while the host has
+    /// the stream at alternate setting 0 and reads no data.
+    pub fn write(&self, data: &[u8]) -> Result<usize> {
+        self.streams.write(data)
+    }
+
+    /// Write audio frames as `write()` does, first checking that `data` holds
+    /// a whole number of frames: a multiple of the subslot size of the format
+    /// the host selected times the channel count. Returns `PartialFrame`
+    /// otherwise, without writing, since a packet ending mid frame shifts
+    /// every following sample onto the wrong channel.
+    pub fn write_frame(&self, data: &[u8]) -> Result<usize> {
+        self.streams.write_frame(data)
+    }
+
+    /// Whether the input endpoint can take a new packet: the host has the
+    /// stream open and the packet of the last `write()` has been collected.
+    /// Check it before assembling a packet to avoid one `write()` would reject
+    /// with `WouldBlock`. The host collects one packet per polling interval,
+    /// every frame unless `StreamConfig::with_interval()` says otherwise, so
+    /// this becomes true again once per interval. It relies on the bus
+    /// reporting IN completions for isochronous endpoints through
+    /// `UsbDevice::poll`.
+    pub fn input_ready(&self) -> bool {
+        self.streams.input_ready()
+    }
+
+    /// Whether a packet from the host is waiting on the output endpoint, so
+    /// that the next `read()` returns it rather than `WouldBlock`. A main loop
+    /// can check this, then read, without matching on `UsbError::WouldBlock`.
+    /// It is set when `UsbDevice::poll` reports the endpoint received data
+    /// and cleared by `read()`, and is never set with `discard_output()`.
+    pub fn read_ready(&self) -> bool {
+        self.streams.read_ready()
+    }
+
+    /// Send the device's measured consumption rate of the output stream, in
+    /// Hz, on the feedback endpoint. Returns an error if no output stream has
+    /// been configured, or if it is not asynchronous and so has no feedback
+    /// endpoint.
+    ///
+    /// The host polls the feedback endpoint once per `feedback_refresh()`
+    /// period, every 8 ms by default. Write a value at least once per period,
+    /// e.g. on every SOF or whenever the rate measurement updates. Only one
+    /// packet can be queued at a time, so a write made while the previous
+    /// value still waits for the host's poll returns `WouldBlock`; the queued
+    /// value goes out on that poll. A poll that finds nothing queued gets an
+    /// empty packet, and hosts keep using the last value they received, so an
+    /// occasional missed period is harmless. Stopping feedback altogether
+    /// leaves the host at its last value, free to drift.
+    pub fn write_feedback(&self, rate: u32) -> Result<usize> {
+        self.streams.write_feedback(rate)
+    }
+
+    /// Send feedback for a consumption rate that deviates from the output
+    /// stream's current rate, as last set by the host on its clock, by `ppm`
+    /// parts per million, as computed by `ppm_error()` from a measured rate.
+    /// Returns an error if no output stream has been configured.
+    pub fn write_feedback_ppm(&self, ppm: i32) -> Result<usize> {
+        self.streams.write_feedback_ppm(ppm)
+    }
+
+    /// Get the last feedback value sent for the output stream, decoded back
+    /// to Hz. The fixed point encoding limits this to about 0.06 Hz precision,
+    /// or 0.12 Hz at high speed.
+    pub fn last_feedback(&self) -> Option<u32> {
+        self.streams.last_feedback()
+    }
+
+    /// Get current Alternate Setting of the input stream. Returns an error if
+    /// the stream is not configured.
+    pub fn input_alt_setting(&self) -> Result<u8> {
+        self.streams.input_alt_setting()
+    }
+
+    /// Get current Alternate Setting of the output stream. Returns an error if
+    /// the stream is not configured.
+    pub fn output_alt_setting(&self) -> Result<u8> {
+        self.streams.output_alt_setting()
+    }
+
+    /// Nominal bytes per frame of the stream in the given direction, `In` for
+    /// the input stream and `Out` for the output stream, as for
+    /// `StreamConfig::bytes_per_frame()` but at the rate the host last set on
+    /// the stream's clock and in the subslot of the format it selected (the
+    /// stream's first format while closed). Returns an error if that stream
+    /// is not configured.
+    pub fn bytes_per_frame(&self, direction: UsbDirection) -> Result<u16> {
+        self.streams.bytes_per_frame(direction)
+    }
+
+    /// The wMaxPacketSize the input stream's endpoint was allocated with, the
+    /// largest packet `write()` may send. Returns an error if the stream is
+    /// not configured.
+    pub fn input_max_packet_size(&self) -> Result<u16> {
+        self.streams.input_max_packet_size()
+    }
+
+    /// The wMaxPacketSize the output stream's endpoint was allocated with, the
+    /// largest packet `read()` may receive. Returns an error if the stream is
+    /// not configured.
+    pub fn output_max_packet_size(&self) -> Result<u16> {
+        self.streams.output_max_packet_size()
+    }
+
+    /// The AudioControl interface number, the first of the function's
+    /// interfaces.
+    pub fn control_interface_number(&self) -> u8 {
+        self.control.control_interface_number()
+    }
+
+    /// The input stream's AudioStreaming interface number. Returns an error
+    /// if the stream is not configured.
+    pub fn input_interface_number(&self) -> Result<u8> {
+        self.streams.input_interface_number()
+    }
+
+    /// The output stream's AudioStreaming interface number. Returns an error
+    /// if the stream is not configured.
+    pub fn output_interface_number(&self) -> Result<u8> {
+        self.streams.output_interface_number()
+    }
+
+    /// The address of the input stream's data endpoint. Returns an error if
+    /// the stream is not configured.
+    pub fn input_endpoint_address(&self) -> Result<EndpointAddress> {
+        self.streams.input_endpoint_address()
+    }
+
+    /// The address of the output stream's data endpoint. Returns an error if
+    /// the stream is not configured.
+    pub fn output_endpoint_address(&self) -> Result<EndpointAddress> {
+        self.streams.output_endpoint_address()
+    }
+
+    /// Number of streams the class was built with, each with its own audio
+    /// streaming interface.
+    pub fn stream_count(&self) -> usize {
+        self.streams.stream_count()
+    }
+
+    /// Get the format the host selected for the input stream through its
+    /// alternate setting. Returns `StreamIdle` at alternate setting 0 and an
+    /// error if the stream is not configured. Formats sharing an alternate
+    /// setting cannot be told apart, so this reports the first of them.
+    pub fn input_format(&self) -> Result<Format> {
+        self.streams.input_format()
+    }
+
+    /// Get the format the host selected for the output stream, as for
+    /// `input_format()`.
+    pub fn output_format(&self) -> Result<Format> {
+        self.streams.output_format()
+    }
+
+    /// Get the user data attached to the input stream's config. Returns an
+    /// error if the stream is not configured.
+    pub fn input_user_data(&self) -> Result<u32> {
+        self.streams.input_user_data()
+    }
+
+    /// Get the user data attached to the output stream's config. Returns an
+    /// error if the stream is not configured.
+    pub fn output_user_data(&self) -> Result<u32> {
+        self.streams.output_user_data()
+    }
+
+    /// Whether the host has selected an alternate setting on one of the audio
+    /// streaming interfaces since the last bus reset. Unlike
+    /// `UsbDevice::state()`, which reports `Configured` as soon as the
+    /// configuration is set, this only becomes true once the host driver has
+    /// claimed the audio function.
+    pub fn is_configured(&self) -> bool {
+        self.control.configured
+    }
+
+    /// Get the kind of the last control request answered by the class, if any.
+    pub fn last_control(&self) -> Option<ControlKind> {
+        self.control.last_control
+    }
+
+    /// Take the kind of the last control request answered by the class,
+    /// clearing it. Calling this after every `UsbDevice::poll` tells whether a
+    /// request was handled during that poll.
+    pub fn take_last_control(&mut self) -> Option<ControlKind> {
+        self.control.last_control.take()
+    }
+
+    /// Take the next stream the host opened or closed since the last call,
+    /// e.g. to start or stop the I2S DMA. Each stream whose alternate setting
+    /// changed is reported once, with the setting now selected; changes
+    /// between two calls are coalesced, so a stream opened and closed again
+    /// is reported once as closed. Call it after every `UsbDevice::poll`
+    /// until it returns None.
+    pub fn take_stream_change(&mut self) -> Option<StreamChange> {
+        self.control.take_stream_change(&self.streams)
+    }
+
+    /// Get the copy protection level last set by the host on the output
+    /// terminal, starting at `Cpl0`. Returns None if the copy protect control
+    /// is not enabled.
+    pub fn copy_protect(&self) -> Option<CopyProtect> {
+        self.control.copy_protect
+    }
+
+    /// Get the master mute state set by the host. Returns None if the mute
+    /// control is not enabled.
+    pub fn mute(&self) -> Option<bool> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.mute)
+    }
+
+    /// Get the master volume set by the host in 1/256 dB steps, between -60 dB
+    /// and 0 dB. Returns None if the volume control is not enabled.
+    pub fn volume(&self) -> Option<i16> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.volume)
+    }
+
+    /// Whether logical channel `channel`, counted from 1, is muted, either by
+    /// its own mute control or by the master mute. Returns None if per channel
+    /// mute is not enabled or the channel does not exist.
+    pub fn channel_muted(&self, channel: u8) -> Option<bool> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.channel_muted(channel))
+    }
+
+    /// Get the volume of logical channel `channel`, counted from 1, set by the
+    /// host in 1/256 dB steps, not including the master volume. Returns None
+    /// if per channel volume is not enabled or the channel does not exist.
+    pub fn channel_volume(&self, channel: u8) -> Option<i16> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.channel_volume(channel))
+    }
+
+    /// Get the rate of the first clock source, which the input stream, or the
+    /// output stream when there is no input, runs from. With a clock selector
+    /// this is the rate of the selected clock source.
+    pub fn current_sample_rate(&self) -> Option<SampleRate> {
+        self.control.current_sample_rate()
+    }
+
+    /// Get the input pin of the clock selector the host chose, 1 for the clock
+    /// of `clock_source()` and 2 and on for the sources given to
+    /// `clock_selector()` in order. Returns None without a clock selector.
+    pub fn selected_clock(&self) -> Option<u8> {
+        self.control.selected_clock()
+    }
+
+    /// Get the input pin of the selector unit the host chose, 1 for the input
+    /// stream's own input terminal and 2 and on for the terminals given to
+    /// `input_selector()` in order. Returns None without a selector unit.
+    pub fn selected_input(&self) -> Option<u8> {
+        self.control.selected_input()
+    }
+
+    /// Get the mixer unit's level from logical input channel `input` to
+    /// logical output channel `output`, both counted from 1, in 1/256 dB.
+    /// Input channels run through the pins in order, each pin carrying the
+    /// input terminal's cluster. `i16::MIN` means the input is cut off.
+    /// Returns None without a mixer unit or for channels outside it.
+    pub fn mixer_level(&self, input: u8, output: u8) -> Option<i16> {
+        self.control.mixer_level(input, output)
+    }
+
+    /// Call `f` with every class-specific control request the class, as
+    /// configured, answers. Requests not listed are left unanswered and
+    /// stalled by usb-device.
+    #[cfg(feature = "diagnostics")]
+    pub fn control_routes(&self, f: impl FnMut(ControlRoute)) {
+        self.control.control_routes(&self.streams, f)
+    }
+
+    /// The number of bytes this function adds to the configuration
+    /// descriptor's wTotalLength, for planning a composite device: the IAD,
+    /// the AudioControl and AudioStreaming interfaces with all their class
+    /// and endpoint descriptors, and the vendor descriptor, if any.
+    pub fn config_contribution_len(&self) -> usize {
+        self.control.config_length(&self.streams)
+    }
+
+    /// Set the function latency, in nanoseconds, that the host reads through
+    /// the latency control. Returns an error if the latency control is not
+    /// enabled.
+    pub fn set_function_latency(&mut self, latency: u32) -> Result<()> {
+        self.control.set_function_latency(latency)
+    }
+
+    /// Update the ratio reported by the clock multiplier, e.g. after firmware
+    /// reconfigures the PLL it describes. Returns an error if no clock
+    /// multiplier was added.
+    pub fn set_clock_multiplier(&mut self, numerator: u16, denominator: u16) -> Result<()> {
+        self.control.set_clock_multiplier(numerator, denominator)
+    }
+
+    /// Change the channel cluster reported for the output stream, e.g. when
+    /// speakers are connected or removed. The host reads it through the
+    /// cluster control, and the status interrupt endpoint, when enabled,
+    /// notifies it of the change. Returns an error if the cluster control is
+    /// not enabled, and `StatusQueueFull` if the cluster was changed but the
+    /// notification had to be dropped.
+    pub fn set_output_cluster(&mut self, cluster: Cluster) -> Result<()> {
+        self.control.set_output_cluster(cluster)
+    }
+
+    /// Mark the clock source `clock` valid or invalid, e.g. when an external
+    /// clock gains or loses lock. `clock` is the Clock Source ID of the
+    /// configuration descriptor: 0x01 for `clock_source()`, 0x06 for the
+    /// output's own clock with `independent_clocks()` and 0x0C onwards for
+    /// the further sources of `clock_selector()`, in order. The state is
+    /// reported through the clock validity control, and when the status
+    /// interrupt endpoint is enabled a change also notifies the host so it
+    /// can stop streaming from a lost clock.
+    ///
+    /// Clocks start out invalid so that the host waits rather than streaming
+    /// into a clock that has not locked yet. Firmware must call this with
+    /// `true` once a clock is running, typically right after `build()`.
+    /// Returns `UnknownClock` if the function has no clock `clock`, and
+    /// `StatusQueueFull` if the change was made but the notification had to
+    /// be dropped.
+    pub fn set_clock_valid(&mut self, clock: u8, valid: bool) -> Result<()> {
+        self.control.set_clock_valid(clock, valid)
+    }
+
+    /// Notify the host through the status interrupt endpoint that the CUR
+    /// value of `control` (a control selector) on `entity` (the terminal,
+    /// unit or clock ID of the configuration descriptor) changed, for a
+    /// control the class does not report by itself, such as an overrange or
+    /// a firmware adjusted volume. The host answers by reading the control.
+    /// Messages are sent in order from `poll()`. Returns an error if the
+    /// status interrupt endpoint is not enabled or the queue is full.
+    pub fn notify_control_change(&mut self, entity: u8, control: u8) -> Result<()> {
+        self.control.notify_control_change(entity, control)
+    }
+
+}
+
+impl<B: UsbBus> UsbClass<B> for AudioClass<'_, B> {
+
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+        self.control.get_configuration_descriptors(&self.streams, writer)
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        self.control.control_out(&self.streams, xfer)
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        self.control.control_in(&self.streams, xfer)
+    }
+
+    fn get_string(&self, index: StringIndex, _lang_id: LangID) -> Option<&str> {
+        self.control.get_string(index).or_else(|| self.streams.get_string(index))
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        self.streams.endpoint_in_complete(addr)
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if self.control.discard_output {
+            self.streams.discard_output(addr)
+        } else {
+            self.streams.endpoint_out(addr)
+        }
+    }
+
+    fn reset(&mut self) {
+        self.control.reset(&self.streams)
+    }
+
+    fn poll(&mut self) {
+        self.control.poll(&self.streams)
+    }
+
+}
+
+
+
+/// AUDIO CONTROL
+/// The control half of a split `AudioClass`, polled by `UsbDevice`
+pub struct AudioControl<'c, 'a, B: UsbBus> {
+    streams: &'c AudioStreams<'a, B>,
+    control: &'c mut ControlState<'a, B>,
+}
+
+impl<B: UsbBus> AudioControl<'_, '_, B> {
+
+    /// See `AudioClass::is_configured()`
+    pub fn is_configured(&self) -> bool {
+        self.control.configured
+    }
+
+    /// See `AudioClass::last_control()`
+    pub fn last_control(&self) -> Option<ControlKind> {
+        self.control.last_control
+    }
+
+    /// See `AudioClass::take_last_control()`
+    pub fn take_last_control(&mut self) -> Option<ControlKind> {
+        self.control.last_control.take()
+    }
+
+    /// See `AudioClass::take_stream_change()`
+    pub fn take_stream_change(&mut self) -> Option<StreamChange> {
+        self.control.take_stream_change(self.streams)
+    }
+
+    /// See `AudioClass::copy_protect()`
+    pub fn copy_protect(&self) -> Option<CopyProtect> {
+        self.control.copy_protect
+    }
+
+    /// See `AudioClass::mute()`
+    pub fn mute(&self) -> Option<bool> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.mute)
+    }
+
+    /// See `AudioClass::volume()`
+    pub fn volume(&self) -> Option<i16> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.volume)
+    }
+
+    /// See `AudioClass::channel_muted()`
+    pub fn channel_muted(&self, channel: u8) -> Option<bool> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.channel_muted(channel))
+    }
+
+    /// See `AudioClass::channel_volume()`
+    pub fn channel_volume(&self, channel: u8) -> Option<i16> {
+        self.control.feature_unit.as_ref().and_then(|feature| feature.channel_volume(channel))
+    }
+
+    /// See `AudioClass::current_sample_rate()`
+    pub fn current_sample_rate(&self) -> Option<SampleRate> {
+        self.control.current_sample_rate()
+    }
+
+    /// See `AudioClass::selected_clock()`
+    pub fn selected_clock(&self) -> Option<u8> {
+        self.control.selected_clock()
+    }
+
+    /// See `AudioClass::selected_input()`
+    pub fn selected_input(&self) -> Option<u8> {
+        self.control.selected_input()
+    }
+
+    /// See `AudioClass::control_interface_number()`
+    pub fn control_interface_number(&self) -> u8 {
+        self.control.control_interface_number()
+    }
+
+    /// See `AudioClass::mixer_level()`
+    pub fn mixer_level(&self, input: u8, output: u8) -> Option<i16> {
+        self.control.mixer_level(input, output)
+    }
+
+    /// See `AudioClass::set_clock_multiplier()`
+    pub fn set_clock_multiplier(&mut self, numerator: u16, denominator: u16) -> Result<()> {
+        self.control.set_clock_multiplier(numerator, denominator)
+    }
+
+    /// See `AudioClass::config_contribution_len()`
+    pub fn config_contribution_len(&self) -> usize {
+        self.control.config_length(self.streams)
+    }
+
+    /// See `AudioClass::set_function_latency()`
+    pub fn set_function_latency(&mut self, latency: u32) -> Result<()> {
+        self.control.set_function_latency(latency)
+    }
+
+    /// See `AudioClass::set_output_cluster()`
+    pub fn set_output_cluster(&mut self, cluster: Cluster) -> Result<()> {
+        self.control.set_output_cluster(cluster)
+    }
+
+    /// See `AudioClass::set_clock_valid()`
+    pub fn set_clock_valid(&mut self, clock: u8, valid: bool) -> Result<()> {
+        self.control.set_clock_valid(clock, valid)
+    }
+
+    /// See `AudioClass::notify_control_change()`
+    pub fn notify_control_change(&mut self, entity: u8, control: u8) -> Result<()> {
+        self.control.notify_control_change(entity, control)
+    }
+
+}
+
+impl<B: UsbBus> UsbClass<B> for AudioControl<'_, '_, B> {
+
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+        self.control.get_configuration_descriptors(self.streams, writer)
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        self.control.control_out(self.streams, xfer)
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        self.control.control_in(self.streams, xfer)
+    }
+
+    fn get_string(&self, index: StringIndex, _lang_id: LangID) -> Option<&str> {
+        self.control.get_string(index).or_else(|| self.streams.get_string(index))
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        self.streams.endpoint_in_complete(addr)
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if self.control.discard_output {
+            self.streams.discard_output(addr)
+        } else {
+            self.streams.endpoint_out(addr)
+        }
+    }
+
+    fn reset(&mut self) {
+        self.control.reset(self.streams)
+    }
+
+    fn poll(&mut self) {
+        self.control.poll(self.streams)
+    }
+
+}
+
+
+
+/// AUDIO STREAMS
+/// The streaming half of a split `AudioClass`, moving the audio data
+pub struct AudioStreams<'a, B: UsbBus> {
+    input: Option<AudioStream<'a, B, In>>,
+    output: Option<AudioStream<'a, B, Out>>,
+}
+
+impl<B: UsbBus> AudioStreams<'_, B> {
+
+    /// See `AudioClass::read()`
+    pub fn read(&self, data: &mut [u8]) -> Result<usize> {
+
+        if let Some(ref output) = self.output {
+            if output.alt_setting.load(Ordering::Relaxed) == DEFAULT_ALTERNATE_SETTING {
+                return Err(Error::StreamIdle);
+            }
+
+            output.received.store(false, Ordering::Relaxed);
+            output.endpoint.read(data).map_err(Error::UsbError)
+        } else {
+            Err(Error::StreamNotInitialized)
+        }
+
+    }
+
+    /// See `AudioClass::read_frame()`
+    pub fn read_frame(&self, data: &mut [u8]) -> Result<FrameRead> {
+        let output = self.output.as_ref().ok_or(Error::StreamNotInitialized)?;
+        let frame_size = output.frame_size().ok_or(Error::StreamIdle)?;
+
+        let n = self.read(data)?;
+
+        Ok(FrameRead {
+            frames: n / frame_size,
+            leftover: n % frame_size,
+        })
+    }
+
+    /// See `AudioClass::write()`
+    pub fn write(&self, data: &[u8]) -> Result<usize> {
+        if let Some(ref input) = self.input {
+            if input.alt_setting.load(Ordering::Relaxed) == DEFAULT_ALTERNATE_SETTING {
+                return Err(Error::StreamIdle);
+            }
+
+            let n = input.endpoint.write(data).map_err(Error::UsbError)?;
+
+            input.in_flight.store(true, Ordering::Relaxed);
+            Ok(n)
+        } else {
+            Err(Error::StreamNotInitialized)
+        }
+    }
+
+    /// See `AudioClass::write_frame()`
+    pub fn write_frame(&self, data: &[u8]) -> Result<usize> {
+        let input = self.input.as_ref().ok_or(Error::StreamNotInitialized)?;
+        let frame_size = input.frame_size().ok_or(Error::StreamIdle)?;
+
+        if !data.len().is_multiple_of(frame_size) {
+            return Err(Error::PartialFrame);
+        }
+
+        self.write(data)
+    }
+
+    /// See `AudioClass::input_ready()`
+    pub fn input_ready(&self) -> bool {
+        self.input.as_ref().is_some_and(|input| {
+            input.alt_setting.load(Ordering::Relaxed) != DEFAULT_ALTERNATE_SETTING
+                && !input.in_flight.load(Ordering::Relaxed)
+        })
+    }
+
+    // read and drop a packet received on the output endpoint
+    fn discard_output(&self, addr: EndpointAddress) {
+        if let Some(output) = self.output.as_ref() {
+            if addr == output.endpoint.address() {
+                let mut packet = [0u8; 1024];
+                output.endpoint.read(&mut packet).ok();
+            }
+        }
+    }
+
+    // fill an idle frame of the open input stream with a zero-length packet
+    fn write_zlp(&self) {
+        if self.input_ready() {
+            self.write(&[]).ok();
+        }
+    }
+
+    fn endpoint_in_complete(&self, addr: EndpointAddress) {
+        if let Some(input) = self.input.as_ref() {
+            if addr == input.endpoint.address() {
+                input.in_flight.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// See `AudioClass::read_ready()`
+    pub fn read_ready(&self) -> bool {
+        self.output.as_ref().is_some_and(|output| output.received.load(Ordering::Relaxed))
+    }
+
+    fn endpoint_out(&self, addr: EndpointAddress) {
+        if let Some(output) = self.output.as_ref() {
+            if addr == output.endpoint.address() {
+                output.received.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// See `AudioClass::write_feedback()`
+    pub fn write_feedback(&self, rate: u32) -> Result<usize> {
+        let output = self.output.as_ref().ok_or(Error::StreamNotInitialized)?;
+
+        output.write_feedback(feedback::encode(rate, output.speed))
+    }
+
+    /// See `AudioClass::write_feedback_ppm()`
+    pub fn write_feedback_ppm(&self, ppm: i32) -> Result<usize> {
+        let output = self.output.as_ref().ok_or(Error::StreamNotInitialized)?;
+
+        output.write_feedback(feedback::encode_ppm(output.rate.load(Ordering::Relaxed), ppm, output.speed))
+    }
+
+    /// See `AudioClass::last_feedback()`
+    pub fn last_feedback(&self) -> Option<u32> {
+        self.output
+            .as_ref()
+            .map(|output| (output.last_feedback.load(Ordering::Relaxed), output.speed))
+            .filter(|&(value, _)| value != 0)
+            .map(|(value, speed)| feedback::decode(value, speed))
+    }
+
+    /// See `AudioClass::input_alt_setting()`
+    pub fn input_alt_setting(&self) -> Result<u8> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.alt_setting.load(Ordering::Relaxed))
+    }
+
+    /// See `AudioClass::output_alt_setting()`
+    pub fn output_alt_setting(&self) -> Result<u8> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.alt_setting.load(Ordering::Relaxed))
+    }
+
+    /// See `AudioClass::bytes_per_frame()`
+    pub fn bytes_per_frame(&self, direction: UsbDirection) -> Result<u16> {
+        let bytes = match direction {
+            UsbDirection::In => self.input.as_ref().map(AudioStream::bytes_per_frame),
+            UsbDirection::Out => self.output.as_ref().map(AudioStream::bytes_per_frame),
+        };
+
+        bytes.ok_or(Error::StreamNotInitialized)
+    }
+
+    /// See `AudioClass::input_max_packet_size()`
+    pub fn input_max_packet_size(&self) -> Result<u16> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.endpoint.max_packet_size())
+    }
+
+    /// See `AudioClass::output_max_packet_size()`
+    pub fn output_max_packet_size(&self) -> Result<u16> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|so| so.endpoint.max_packet_size())
+    }
+
+    /// See `AudioClass::input_interface_number()`
+    pub fn input_interface_number(&self) -> Result<u8> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.interface.into())
+    }
+
+    /// See `AudioClass::output_interface_number()`
+    pub fn output_interface_number(&self) -> Result<u8> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|so| so.interface.into())
+    }
+
+    /// See `AudioClass::input_endpoint_address()`
+    pub fn input_endpoint_address(&self) -> Result<EndpointAddress> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.endpoint.address())
+    }
+
+    /// See `AudioClass::output_endpoint_address()`
+    pub fn output_endpoint_address(&self) -> Result<EndpointAddress> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|so| so.endpoint.address())
+    }
+
+    fn get_string(&self, index: StringIndex) -> Option<&str> {
+        self.input
+            .as_ref()
+            .and_then(|si| si.get_string(index))
+            .or_else(|| self.output.as_ref().and_then(|so| so.get_string(index)))
+    }
+
+    /// See `AudioClass::stream_count()`
+    pub fn stream_count(&self) -> usize {
+        self.input.is_some() as usize + self.output.is_some() as usize
+    }
+
+    /// See `AudioClass::input_format()`
+    pub fn input_format(&self) -> Result<Format> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)?
+            .format()
+            .ok_or(Error::StreamIdle)
+    }
+
+    /// See `AudioClass::output_format()`
+    pub fn output_format(&self) -> Result<Format> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)?
+            .format()
+            .ok_or(Error::StreamIdle)
+    }
+
+    /// See `AudioClass::input_user_data()`
+    pub fn input_user_data(&self) -> Result<u32> {
+        self.input
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.stream_config.user_data)
+    }
+
+    /// See `AudioClass::output_user_data()`
+    pub fn output_user_data(&self) -> Result<u32> {
+        self.output
+            .as_ref()
+            .ok_or(Error::StreamNotInitialized)
+            .map(|si| si.stream_config.user_data)
+    }
+
+}
+
+
+
+/// DESCRIPTOR LAYOUT
+/// What the length of the function's descriptors depends on, gathered from a
+/// built class or from a builder before anything is allocated, so both sizes
+/// come from the same sums
+struct Layout {
+    n_clocks: u8,
+    selector_pins: u8,
+    input_pins: u8,
+    mixer_pins: u8,
+    mixer_controls: usize,
+    multiplier: bool,
+    input: bool,
+    input_sink: bool,
+    output: bool,
+    // only an asynchronous output has a feedback endpoint
+    output_feedback: bool,
+    feature_unit: bool,
+    effect_unit: bool,
+    // the units go on the output path when there is one
+    unit_channels: u8,
+    status_endpoint: bool,
+    input_alts: u8,
+    output_alts: u8,
+    extra_descriptor: Option<usize>,
+    audio_1_0: bool,
+    // discrete rates listed by each 1.0 format type descriptor
+    n_rates: usize,
+}
+
+impl Layout {
+
+    // wTotalLength of the AC header: the header and every class-specific
+    // AudioControl descriptor after it
+    fn ac_length(&self) -> u16 {
+
+        let n_interfaces: u16 = self.input as u16 + self.output as u16;
+
+        let n_sinks: u16 = if self.input && self.input_sink { 1 } else { 0 };
+
+        // a header listing the streaming interfaces, and per stream an input
+        // and an output terminal; there are no clocks or units in 1.0
+        if self.audio_1_0 {
+            return 8 + n_interfaces + (21 * n_interfaces) + (9 * n_sinks);
+        }
+
+        let unit_channels: u16 = self.unit_channels as u16;
+
+        let units_length: u16 = if self.feature_unit { 10 + 4 * unit_channels } else { 0 }
+            + if self.effect_unit { 12 + 4 * unit_channels } else { 0 };
+
+        let multiplier_length: u16 = if self.multiplier { 7 } else { 0 };
+
+        let selector_length: u16 = if self.selector_pins > 0 { 7 + self.selector_pins as u16 } else { 0 };
+
+        // the selector unit and the input terminals past the first
+        let input_selector_length: u16 = if self.input_pins > 0 { 7 + self.input_pins as u16 + 17 * (self.input_pins as u16 - 1) } else { 0 };
+
+        // the mixer unit with its bitmap and the input terminals it adds
+        let mixer_length: u16 = if self.mixer_pins > 0 {
+            13 + self.mixer_pins as u16 + self.mixer_controls.div_ceil(8) as u16 + 17 * (self.mixer_pins as u16 - 1)
+        } else {
+            0
+        };
+
+        9 + (8 * self.n_clocks as u16) + (29 * n_interfaces) + (12 * n_sinks) + units_length + multiplier_length + selector_length + input_selector_length + mixer_length
+    }
+
+    // bytes of every descriptor the function writes to the configuration
+    fn config_length(&self) -> usize {
+
+        // IAD and the AudioControl interface
+        let mut length: usize = 8 + 9 + self.ac_length() as usize;
+
+        if self.status_endpoint {
+            length += 7;
+        }
+
+        // alt 0, then per alt setting the interface, AS_GENERAL, format type
+        // and the data endpoint with its class-specific descriptor. 1.0 has
+        // a shorter AS_GENERAL, lists the rates in the format type and has 9
+        // byte endpoints.
+        let (alt_length, feedback_length) = if self.audio_1_0 {
+            (9 + 7 + (8 + 3 * self.n_rates) + 9 + 7, 9)
+        } else {
+            (9 + 16 + 6 + 7 + 8, 7)
+        };
+
+        if self.input {
+            length += 9 + self.input_alts as usize * alt_length;
+        }
+
+        // the output's alt settings may also carry the feedback endpoint
+        if self.output {
+            let feedback_length: usize = if self.output_feedback { feedback_length } else { 0 };
+
+            length += 9 + self.output_alts as usize * (alt_length + feedback_length);
+        }
+
+        if let Some(len) = self.extra_descriptor {
+            length += 2 + len;
+        }
+
+        length
+    }
+
+}
+
+
+
+// CONTROL STATE
+struct ControlState<'a, B: UsbBus> {
+    control_interface: InterfaceNumber,
+    // the first clock, the output's independent one, then the selectable ones
+    clocks: [Option<Clock<'a>>; 2 + MAX_SELECTABLE_CLOCKS],
+    clock_selector: Option<ClockSelector>,
+    input_selector: Option<SelectorUnit<'a>>,
+    mixer_unit: Option<MixerUnit<'a>>,
+    last_control: Option<ControlKind>,
+    // bit 0 set while an input stream change is untaken, bit 1 for output
+    stream_changes: u8,
+    category: Category,
+    status_endpoint: Option<Endpoint<'a, B, In>>,
+    status_queue: StatusQueue,
+    copy_protect: Option<CopyProtect>,
+    configured: bool,
+    input_sink: Option<TerminalType>,
+    effect_unit: Option<EffectUnit>,
+    alt_setting_controls: bool,
+    cluster: Option<Cluster>,
+    extra_descriptor: Option<(u8, &'a [u8])>,
+    feature_unit: Option<FeatureUnit>,
+    associate_terminals: bool,
+    clock_multiplier: Option<ClockMultiplier>,
+    function_latency: Option<u32>,
+    input_zlp: bool,
+    discard_output: bool,
+    interface_order: InterfaceOrder,
+    audio_1_0: bool,
+    // feedback refreshed every 2^feedback_refresh frames, bRefresh in 1.0
+    feedback_refresh: u8,
+}
+
+impl<'a, B: UsbBus> ControlState<'a, B> {
+
+    fn units(&self) -> Units<'_> {
+        Units {
+            feature: self.feature_unit.as_ref(),
+            effect: self.effect_unit.as_ref(),
+        }
+    }
+
+    fn set_clock_valid(&mut self, id: u8, valid: bool) -> Result<()> {
+        let interface: u8 = self.control_interface.into();
+
+        let clock = self.clocks.iter_mut()
+            .flatten()
+            .find(|clock| clock.id == id)
+            .ok_or(Error::UnknownClock)?;
+
+        if clock.valid == valid {
+            return Ok(());
+        }
+
+        clock.valid = valid;
+
+        if self.status_endpoint.is_some()
+            && !self.status_queue.push(interface, id, CS_CLOCK_VALID_CONTROL, 0x00)
+        {
+            return Err(Error::StatusQueueFull);
+        }
+
+        Ok(())
+    }
+
+    fn notify_control_change(&mut self, entity: u8, control: u8) -> Result<()> {
+        if self.status_endpoint.is_none() {
+            return Err(Error::ControlNotEnabled);
+        }
+
+        let interface: u8 = self.control_interface.into();
+
+        if !self.status_queue.push(interface, entity, control, 0x00) {
+            return Err(Error::StatusQueueFull);
+        }
+
+        Ok(())
+    }
+
+    fn current_sample_rate(&self) -> Option<SampleRate> {
+        let id = self.clock_selector.as_ref().map_or(ID_CLOCK_SRC, |selector| ClockSelector::source_id(selector.selected));
+
+        self.clocks
+            .iter()
+            .flatten()
+            .find(|clock| clock.id == id)
+            .map(|clock| SampleRate::from_hz(clock.rate))
+    }
+
+    fn stream_clock(&self, id: u8) -> Option<&Clock<'a>> {
+        self.clocks.iter().flatten().find(|clock| clock.id == id)
+    }
+
+    fn selected_clock(&self) -> Option<u8> {
+        self.clock_selector.as_ref().map(|selector| selector.selected)
+    }
+
+    fn selected_input(&self) -> Option<u8> {
+        self.input_selector.as_ref().map(|selector| selector.selected)
+    }
+
+    fn mixer_level(&self, input: u8, output: u8) -> Option<i16> {
+        self.mixer_unit.as_ref()?.level(input, output)
+    }
+
+    fn control_interface_number(&self) -> u8 {
+        self.control_interface.into()
+    }
+
+    fn get_string(&self, index: StringIndex) -> Option<&str> {
+        self.clocks
+            .iter()
+            .flatten()
+            .find_map(|clock| clock.get_string(index))
+    }
+
+    // the rate of the clock source a stream runs from, through the clock
+    // selector when it has one
+    fn stream_rate(&self, clock_id: u8) -> Option<u32> {
+        let id = match self.clock_selector.as_ref() {
+            Some(selector) if clock_id == ID_CLOCK_SELECTOR => ClockSelector::source_id(selector.selected),
+            _ => clock_id,
+        };
+
+        self.stream_clock(id).map(|clock| clock.rate)
+    }
+
+    // mirror the clock rates into the streams, which cannot see the clocks
+    // once the class is split
+    fn sync_stream_rates(&self, streams: &AudioStreams<B>) {
+        if let Some(input) = streams.input.as_ref() {
+            if let Some(rate) = self.stream_rate(input.clock_id) {
+                input.rate.store(rate, Ordering::Relaxed);
+            }
+        }
+
+        if let Some(output) = streams.output.as_ref() {
+            if let Some(rate) = self.stream_rate(output.clock_id) {
+                output.rate.store(rate, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn control_routes(&self, streams: &AudioStreams<B>, mut f: impl FnMut(ControlRoute)) {
+        let interface: u8 = self.control_interface.into();
+
+        let mut route = |entity: u8, interface: u8, selector: Option<u8>, request: u8, direction: UsbDirection, kind: ControlKind| {
+            f(ControlRoute {
+                index: (entity as u16) << 8 | interface as u16,
+                selector,
+                request,
+                direction,
+                kind,
+            })
+        };
+
+        if self.function_latency.is_some() {
+            route(0x00, interface, Some(AC_LATENCY_CONTROL), CUR, UsbDirection::In, ControlKind::Latency);
+        }
+
+        for clock in self.clocks.iter().flatten() {
+            if clock.source.frequency_control {
+                route(clock.id, interface, Some(CS_SAM_FREQ_CONTROL), RANGE, UsbDirection::In, ControlKind::ClockRange);
+                route(clock.id, interface, Some(CS_SAM_FREQ_CONTROL), CUR, UsbDirection::In, ControlKind::ClockCur);
+                route(clock.id, interface, Some(CS_SAM_FREQ_CONTROL), CUR, UsbDirection::Out, ControlKind::ClockCur);
+            }
+
+            route(clock.id, interface, Some(CS_CLOCK_VALID_CONTROL), CUR, UsbDirection::In, ControlKind::ClockValid);
+        }
+
+        if self.clock_selector.is_some() {
+            route(ID_CLOCK_SELECTOR, interface, Some(CX_CLOCK_SELECTOR_CONTROL), CUR, UsbDirection::In, ControlKind::ClockSelector);
+            route(ID_CLOCK_SELECTOR, interface, Some(CX_CLOCK_SELECTOR_CONTROL), CUR, UsbDirection::Out, ControlKind::ClockSelector);
+        }
+
+        if self.input_selector.is_some() {
+            route(ID_SELECTOR_UNIT, interface, Some(SU_SELECTOR_CONTROL), CUR, UsbDirection::In, ControlKind::InputSelector);
+            route(ID_SELECTOR_UNIT, interface, Some(SU_SELECTOR_CONTROL), CUR, UsbDirection::Out, ControlKind::InputSelector);
+        }
+
+        if self.mixer_unit.is_some() {
+            route(ID_MIXER_UNIT, interface, Some(MU_MIXER_CONTROL), CUR, UsbDirection::In, ControlKind::Mixer);
+            route(ID_MIXER_UNIT, interface, Some(MU_MIXER_CONTROL), RANGE, UsbDirection::In, ControlKind::Mixer);
+            route(ID_MIXER_UNIT, interface, Some(MU_MIXER_CONTROL), CUR, UsbDirection::Out, ControlKind::Mixer);
+        }
+
+        if self.clock_multiplier.is_some() {
+            route(ID_CLOCK_MULTIPLIER, interface, Some(CM_NUMERATOR_CONTROL), CUR, UsbDirection::In, ControlKind::ClockMultiplier);
+            route(ID_CLOCK_MULTIPLIER, interface, Some(CM_DENOMINATOR_CONTROL), CUR, UsbDirection::In, ControlKind::ClockMultiplier);
+        }
+
+        if self.copy_protect.is_some() {
+            route(ID_OUTPUT_TERMINAL, interface, Some(COPY_PROTECT_CONTROL), CUR, UsbDirection::In, ControlKind::CopyProtect);
+            route(ID_OUTPUT_TERMINAL, interface, Some(COPY_PROTECT_CONTROL), CUR, UsbDirection::Out, ControlKind::CopyProtect);
+        }
+
+        if self.cluster.is_some() {
+            route(ID_OUTPUT_STREAMING, interface, Some(TE_CLUSTER_CONTROL), CUR, UsbDirection::In, ControlKind::Cluster);
+        }
+
+        if let Some(ref feature) = self.feature_unit {
+            if feature.mute.is_some() {
+                route(ID_FEATURE_UNIT, interface, Some(MUTE_CONTROL), CUR, UsbDirection::In, ControlKind::Mute);
+                route(ID_FEATURE_UNIT, interface, Some(MUTE_CONTROL), CUR, UsbDirection::Out, ControlKind::Mute);
+            }
+
+            if feature.volume.is_some() {
+                route(ID_FEATURE_UNIT, interface, Some(VOLUME_CONTROL), CUR, UsbDirection::In, ControlKind::Volume);
+                route(ID_FEATURE_UNIT, interface, Some(VOLUME_CONTROL), RANGE, UsbDirection::In, ControlKind::Volume);
+                route(ID_FEATURE_UNIT, interface, Some(VOLUME_CONTROL), CUR, UsbDirection::Out, ControlKind::Volume);
+            }
+        }
+
+        if self.effect_unit.is_some() {
+            for request in [CUR, RANGE] {
+                route(ID_EFFECT_UNIT, interface, None, request, UsbDirection::In, ControlKind::Effect);
+            }
+
+            route(ID_EFFECT_UNIT, interface, None, CUR, UsbDirection::Out, ControlKind::Effect);
+        }
+
+        if self.alt_setting_controls {
+            let interfaces = streams.input.as_ref().map(|si| si.interface)
+                .into_iter()
+                .chain(streams.output.as_ref().map(|si| si.interface));
+
+            for stream_interface in interfaces {
+                for selector in [AS_ACT_ALT_SETTING_CONTROL, AS_VAL_ALT_SETTINGS_CONTROL] {
+                    route(0x00, stream_interface.into(), Some(selector), CUR, UsbDirection::In, ControlKind::AltSettings);
+                }
+            }
+        }
+    }
+
+    fn take_stream_change(&mut self, streams: &AudioStreams<B>) -> Option<StreamChange> {
+        if self.stream_changes & 0b01 != 0 {
+            self.stream_changes &= !0b01;
+
+            if let Some(input) = streams.input.as_ref() {
+                return Some(StreamChange {
+                    direction: UsbDirection::In,
+                    interface: input.interface.into(),
+                    alt_setting: input.alt_setting.load(Ordering::Relaxed),
+                });
+            }
+        }
+
+        if self.stream_changes & 0b10 != 0 {
+            self.stream_changes &= !0b10;
+
+            if let Some(output) = streams.output.as_ref() {
+                return Some(StreamChange {
+                    direction: UsbDirection::Out,
+                    interface: output.interface.into(),
+                    alt_setting: output.alt_setting.load(Ordering::Relaxed),
+                });
+            }
+        }
+
+        None
+    }
+
+    fn set_function_latency(&mut self, latency: u32) -> Result<()> {
+        let current = self.function_latency.as_mut().ok_or(Error::ControlNotEnabled)?;
+
+        *current = latency;
+
+        Ok(())
+    }
+
+    fn set_clock_multiplier(&mut self, numerator: u16, denominator: u16) -> Result<()> {
+        let multiplier = self.clock_multiplier.as_mut().ok_or(Error::ControlNotEnabled)?;
+
+        multiplier.numerator = numerator;
+        multiplier.denominator = denominator;
+
+        Ok(())
+    }
+
+    fn set_output_cluster(&mut self, cluster: Cluster) -> Result<()> {
+        let current = self.cluster.as_mut().ok_or(Error::ControlNotEnabled)?;
+
+        if *current != cluster {
+            *current = cluster;
+
+            if self.status_endpoint.is_some() {
+                let interface: u8 = self.control_interface.into();
+                if !self.status_queue.push(interface, ID_OUTPUT_STREAMING, TE_CLUSTER_CONTROL, 0x00) {
+                    return Err(Error::StatusQueueFull);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn layout(&self, streams: &AudioStreams<B>) -> Layout {
+        Layout {
+            n_clocks: self.clocks.iter().flatten().count() as u8,
+            selector_pins: self.clock_selector.as_ref().map_or(0, |selector| selector.n_pins),
+            input_pins: self.input_selector.as_ref().map_or(0, SelectorUnit::n_pins),
+            mixer_pins: self.mixer_unit.as_ref().map_or(0, MixerUnit::n_pins),
+            mixer_controls: self.mixer_unit.as_ref().map_or(0, MixerUnit::n_controls),
+            multiplier: self.clock_multiplier.is_some(),
+            input: streams.input.is_some(),
+            input_sink: self.input_sink.is_some(),
+            output: streams.output.is_some(),
+            output_feedback: streams.output.as_ref().is_some_and(|output| output.feedback.is_some()),
+            feature_unit: self.feature_unit.is_some(),
+            effect_unit: self.effect_unit.is_some(),
+            unit_channels: match (&streams.output, &streams.input) {
+                (Some(output), _) => output.stream_config.n_channels,
+                (None, Some(input)) => input.stream_config.n_channels,
+                (None, None) => 0,
+            },
+            status_endpoint: self.status_endpoint.is_some(),
+            input_alts: streams.input.as_ref().map_or(0, |input| input.stream_config.alt_settings()),
+            output_alts: streams.output.as_ref().map_or(0, |output| output.stream_config.alt_settings()),
+            extra_descriptor: self.extra_descriptor.map(|(_, data)| data.len()),
+            audio_1_0: self.audio_1_0,
+            n_rates: self.clocks[0].as_ref().map_or(1, |clock| clock.supported_rates().len()),
+        }
+    }
+
+    fn ac_length(&self, streams: &AudioStreams<B>) -> u16 {
+        self.layout(streams).ac_length()
+    }
+
+    fn config_length(&self, streams: &AudioStreams<B>) -> usize {
+        self.layout(streams).config_length()
+    }
+
+    fn get_configuration_descriptors(&self, streams: &AudioStreams<B>, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+        // PREAMBLE CALCULATIONS
+        let n_interfaces: u8 = streams.stream_count() as u8;
+
+        let ac_length: u16 = self.ac_length(streams);
+
+        // INTERFACE ASSOCIATION DESCRIPTOR
+        writer.write(0x0B, &[
+            self.control_interface.into(), // first interface, allocated before the streams
+            n_interfaces + 1, // number of interfaces
+            AUDIO_FUNCTION,
+            FUNCTION_SUBCLASS_UNDEFINED,
+            if self.audio_1_0 { FUNCTION_PROTOCOL_UNDEFINED } else { AF_VERSION_02_00 },
+            0x00,
+        ])?;
+
+        if self.interface_order == InterfaceOrder::StreamingFirst {
+            self.streaming_descriptors(streams, writer)?;
+        }
+
+        // BASE INTERFACE DESCRIPTOR
+        writer.interface(self.control_interface, AUDIO, AUDIOCONTROL, if self.audio_1_0 { IP_UNDEFINED } else { IP_VERSION_02_00 })?;
+
+        // AUDIO CONTROL DESCRIPTORS
+        let ac_start = writer.position();
+
+        if self.audio_1_0 {
+            uac1::ac_descriptors(self, streams, writer, ac_length)?;
+        } else {
+            self.ac_descriptors(streams, writer, ac_length)?;
+        }
+
+        // the header's wTotalLength is written before the descriptors it
+        // covers, so check it against what was actually emitted rather than
+        // enumerate with a wrong total
+        if writer.position() - ac_start != ac_length as usize {
+            return Err(UsbError::InvalidState);
+        }
+
+        // STATUS INTERRUPT ENDPOINT
+        if let Some(ref endpoint) = self.status_endpoint {
+            writer.endpoint(endpoint)?;
+        }
+
+        if self.interface_order == InterfaceOrder::ControlFirst {
+            self.streaming_descriptors(streams, writer)?;
+        }
+
+        // VENDOR DESCRIPTOR
+        if let Some((descriptor_type, data)) = self.extra_descriptor {
+            writer.write(descriptor_type, data)?;
+        }
+
+        Ok(())
+
+    }
+
+    /// The AudioControl header and the clock, terminal and unit descriptors
+    fn ac_descriptors(&self, streams: &AudioStreams<B>, writer: &mut DescriptorWriter, ac_length: u16) -> usb_device::Result<()> {
+
+        let total_length: [u8; 2] = ac_length.to_be_bytes();
+
+        // AUDIO CONTROL HEADER
+        let ac_header: [u8; 7] = [
+            HEADER,
+            0x00, // bcdADC 2.00 as big-endian BCD
+            0x02,
+            self.category.into(), // bCategory
+            total_length[1],
+            total_length[0],
+            if self.function_latency.is_some() { 0b01 } else { 0b00 }, // bmControls (latency read only)
+        ];
+
+        writer.write(CS_INTERFACE, &ac_header)?;
+
+        // CLOCK SOURCE DESCRIPTORS
+        for clock in self.clocks.iter().flatten() {
+            clock.descriptor(writer)?;
+        }
+
+        if let Some(ref selector) = self.clock_selector {
+            selector.descriptor(writer)?;
+        }
+
+        if let Some(ref multiplier) = self.clock_multiplier {
+            multiplier.descriptor(writer)?;
+        }
+
+        // AUDIO CONTROL INTERFACE DESCRIPTORS
+        if let Some(ref input) = streams.input {
+            let units = if streams.output.is_none() { self.units() } else { Units { feature: None, effect: None } };
+
+            // the physical terminals of a duplex device may be associated
+            let assoc = if self.associate_terminals && streams.output.is_some() { ID_OUTPUT_TERMINAL } else { 0x00 };
+
+            input.input_ac_descriptor(writer, self.input_sink, self.input_selector.as_ref(), self.mixer_unit.as_ref(), units, assoc)?;
+        }
+
+        if let Some(ref output) = streams.output {
+            // copy protect control: host programmable
+            let terminal_controls: u16 =
+                if self.copy_protect.is_some() { 0b11 } else { 0b00 };
+
+            // cluster control: read only
+            let input_controls: u16 =
+                if self.cluster.is_some() { 0b01000000 } else { 0b00 };
+
+            let assoc = if self.associate_terminals && streams.input.is_some() { ID_INPUT_TERMINAL } else { 0x00 };
+
+            output.output_ac_descriptor(writer, input_controls, terminal_controls, self.units(), assoc)?;
+        }
+
+        Ok(())
+
+    }
+
+    /// Streaming interfaces with their class and endpoint descriptors
+    fn streaming_descriptors(&self, streams: &AudioStreams<B>, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+        if self.audio_1_0 {
+            return uac1::streaming_descriptors(self, streams, writer);
+        }
+
+        // active and valid alternate setting controls: read only
+        let as_controls: u8 =
+            if self.alt_setting_controls { 0b0101 } else { 0b0000 };
+
+        if let Some(ref input) = streams.input {
+            input.input_as_ep_descriptor(writer, as_controls)?;
+        }
+
+        if let Some(ref output) = streams.output {
+            output.output_as_ep_descriptor(writer, as_controls)?;
+        }
+
+        Ok(())
+
+    }
+
+    fn control_out(&mut self, streams: &AudioStreams<B>, xfer: ControlOut<B>) {
+
+        let req = xfer.request();
+
+        if (
+            req.request_type == RequestType::Standard
+            && req.recipient == Recipient::Interface
+            && req.request == Request::SET_INTERFACE
+        ) {
+
+            let interface = req.index as u8;
+            let alt_setting = req.value as u8;
+
+            // only alt 0 and the alternate settings described in the
+            // configuration descriptor can be selected
+            if let Some(input) = streams.input.as_ref() {
+                if interface == input.interface.into() {
+                    if req.value > input.stream_config.alt_settings() as u16 {
+                        xfer.reject().ok();
+                        return;
+                    }
+                    if input.alt_setting.swap(alt_setting, Ordering::Relaxed) != alt_setting {
+                        self.stream_changes |= 0b01;
+                    }
+                    xfer.accept().ok();
+                    self.configured = true;
+                    self.last_control = Some(ControlKind::SetInterface);
+                    return;
+                }
+            }
+
+            if let Some(output) = streams.output.as_ref() {
+                if interface == output.interface.into() {
+                    if req.value > output.stream_config.alt_settings() as u16 {
+                        xfer.reject().ok();
+                        return;
+                    }
+                    if output.alt_setting.swap(alt_setting, Ordering::Relaxed) != alt_setting {
+                        self.stream_changes |= 0b10;
+                    }
+                    xfer.accept().ok();
+                    self.configured = true;
+                    self.last_control = Some(ControlKind::SetInterface);
+                }
+            }
+
+        }
+
+        // none of the 2.0 entities exist in 1.0 mode
+        else if self.audio_1_0 {
+            if let Some(kind) = uac1::control_out(self, streams, xfer) {
+                self.last_control = Some(kind);
+            }
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_OUTPUT_TERMINAL
+                && (req.value >> 8) as u8 == COPY_PROTECT_CONTROL
+                && req.request == CUR
+                && self.copy_protect.is_some()
+        ) {
+
+            match xfer.data().first().copied().and_then(CopyProtect::from_level) {
+                Some(level) => {
+                    self.copy_protect = Some(level);
+                    xfer.accept().ok();
+                    self.last_control = Some(ControlKind::CopyProtect);
+                }
+                None => {
+                    xfer.reject().ok();
+                }
+            }
+
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_CLOCK_SELECTOR
+        ) {
+
+            if let Some(selector) = self.clock_selector.as_mut() {
+                if let Some(kind) = selector.control_out(xfer) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_SELECTOR_UNIT
+        ) {
+
+            if let Some(selector) = self.input_selector.as_mut() {
+                if let Some(kind) = selector.control_out(xfer) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_MIXER_UNIT
+        ) {
+
+            if let Some(mixer) = self.mixer_unit.as_mut() {
+                if let Some(kind) = mixer.control_out(xfer) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+        }
+
+        else if let Some(clock) = self.clocks
+            .iter_mut()
+            .flatten()
+            .find(|clock| (
+                req.request_type == RequestType::Class
+                    && req.recipient == Recipient::Interface
+                    && clock.id == (req.index >> 8) as u8
+            ))
+        {
+            if let Some(kind) = clock.control_out(xfer) {
+                self.last_control = Some(kind);
+            }
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_FEATURE_UNIT
+        ) {
+
+            if let Some(feature) = self.feature_unit.as_mut() {
+                if let Some(kind) = feature.control_out(xfer) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+                && (req.index >> 8) as u8 == ID_EFFECT_UNIT
+        ) {
+
+            if let Some(effect) = self.effect_unit {
+                let request = EffectRequest {
+                    request: req.request,
+                    selector: (req.value >> 8) as u8,
+                    channel: req.value as u8,
+                };
+
+                if (effect.handler)(request, EffectAccess::Set(xfer.data())).is_some() {
+                    xfer.accept().ok();
+                    self.last_control = Some(ControlKind::Effect);
+                } else {
+                    xfer.reject().ok();
                 }
             }
 
-            if let Some(output) = self.output.as_ref() {
-                if interface == output.interface.into() {
-                    xfer.accept_with(&[output.alt_setting]).ok();
-                    return;
-                }
+        }
+
+        // a clock or the clock selector may have changed a stream's rate
+        self.sync_stream_rates(streams);
+
+    }
+
+    fn control_in(&mut self, streams: &AudioStreams<B>, xfer: ControlIn<B>) {
+
+        let req = xfer.request();
+
+        if (
+            req.request_type == RequestType::Standard
+            && req.recipient == Recipient::Interface
+            && req.request == Request::GET_INTERFACE
+        ) {
+            let interface = req.index as u8;
+
+            // the control interface has only alt 0; other classes' interfaces
+            // are left to them
+            if interface == self.control_interface.into() {
+                accept_truncated(xfer, &[DEFAULT_ALTERNATE_SETTING]).ok();
+                self.last_control = Some(ControlKind::GetInterface);
+                return;
+            }
+
+            if let Some(input) = streams.input.as_ref() {
+                if interface == input.interface.into() {
+                    accept_truncated(xfer, &[input.alt_setting.load(Ordering::Relaxed)]).ok();
+                    self.last_control = Some(ControlKind::GetInterface);
+                    return;
+                }
+            }
+
+            if let Some(output) = streams.output.as_ref() {
+                if interface == output.interface.into() {
+                    accept_truncated(xfer, &[output.alt_setting.load(Ordering::Relaxed)]).ok();
+                    self.last_control = Some(ControlKind::GetInterface);
+                }
+            }
+        }
+
+        else if self.audio_1_0 {
+            if let Some(kind) = uac1::control_in(self, streams, xfer) {
+                self.last_control = Some(kind);
+            }
+        }
+
+        else if (
+            req.request_type == RequestType::Class
+                && req.recipient == Recipient::Interface
+        ) {
+
+            let entity = (req.index >> 8) as u8;
+            let interface = req.index as u8;
+
+            let input = streams.input.as_ref().filter(|si| interface == si.interface.into());
+            let output = streams.output.as_ref().filter(|si| interface == si.interface.into());
+
+            // requests to the control interface itself address entity 0
+            if entity == 0x00 && interface == self.control_interface.into() {
+                if let (Some(latency), AC_LATENCY_CONTROL, CUR) = (self.function_latency, (req.value >> 8) as u8, req.request) {
+                    accept_truncated(xfer, &latency.to_le_bytes()).ok();
+                    self.last_control = Some(ControlKind::Latency);
+                }
+            }
+
+            // requests to a streaming interface itself address entity 0
+            else if entity == 0x00 && self.alt_setting_controls && (input.is_some() || output.is_some()) {
+                let kind = match (input, output) {
+                    (Some(input), _) => input.control_in(xfer),
+                    (None, Some(output)) => output.control_in(xfer),
+                    (None, None) => None,
+                };
+
+                if kind.is_some() {
+                    self.last_control = kind;
+                }
+            }
+
+            else if entity == ID_OUTPUT_TERMINAL
+                && (req.value >> 8) as u8 == COPY_PROTECT_CONTROL
+                && req.request == CUR
+            {
+                if let Some(level) = self.copy_protect {
+                    accept_truncated(xfer, &[level as u8]).ok();
+                    self.last_control = Some(ControlKind::CopyProtect);
+                }
+            }
+
+            else if entity == ID_OUTPUT_STREAMING
+                && (req.value >> 8) as u8 == TE_CLUSTER_CONTROL
+                && req.request == CUR
+            {
+                if let Some(cluster) = self.cluster {
+                    accept_truncated(xfer, &cluster.descriptor()).ok();
+                    self.last_control = Some(ControlKind::Cluster);
+                }
+            }
+
+            else if entity == ID_FEATURE_UNIT {
+                if let Some(kind) = self.feature_unit.as_ref().and_then(|feature| feature.control_in(xfer)) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+            else if entity == ID_EFFECT_UNIT {
+                if let Some(effect) = self.effect_unit {
+                    let request = EffectRequest {
+                        request: req.request,
+                        selector: (req.value >> 8) as u8,
+                        channel: req.value as u8,
+                    };
+
+                    let accepted = xfer.accept(|buf| {
+                        (effect.handler)(request, EffectAccess::Get(buf)).ok_or(UsbError::InvalidState)
+                    });
+
+                    if accepted.is_ok() {
+                        self.last_control = Some(ControlKind::Effect);
+                    }
+                }
+            }
+
+            else if entity == ID_CLOCK_MULTIPLIER {
+                if let Some(kind) = self.clock_multiplier.as_ref().and_then(|multiplier| multiplier.control_in(xfer)) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+            else if entity == ID_CLOCK_SELECTOR {
+                if let Some(kind) = self.clock_selector.as_ref().and_then(|selector| selector.control_in(xfer)) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+            else if entity == ID_SELECTOR_UNIT {
+                if let Some(kind) = self.input_selector.as_ref().and_then(|selector| selector.control_in(xfer)) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+            else if entity == ID_MIXER_UNIT {
+                if let Some(kind) = self.mixer_unit.as_ref().and_then(|mixer| mixer.control_in(xfer)) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+            else if let Some(clock) = self.clocks
+                .iter()
+                .flatten()
+                .find(|clock| clock.id == entity)
+            {
+                if let Some(kind) = clock.control_in(xfer) {
+                    self.last_control = Some(kind);
+                }
+            }
+
+        }
+    }
+
+    fn reset(&mut self, streams: &AudioStreams<B>) {
+        self.configured = false;
+
+        if let Some(input) = streams.input.as_ref() {
+            if input.alt_setting.swap(DEFAULT_ALTERNATE_SETTING, Ordering::Relaxed) != DEFAULT_ALTERNATE_SETTING {
+                self.stream_changes |= 0b01;
+            }
+            input.in_flight.store(false, Ordering::Relaxed);
+        }
+
+        if let Some(output) = streams.output.as_ref() {
+            if output.alt_setting.swap(DEFAULT_ALTERNATE_SETTING, Ordering::Relaxed) != DEFAULT_ALTERNATE_SETTING {
+                self.stream_changes |= 0b10;
+            }
+            output.received.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn poll(&mut self, streams: &AudioStreams<B>) {
+        if let Some(ref endpoint) = self.status_endpoint {
+            self.status_queue.flush(endpoint);
+        }
+
+        if self.input_zlp {
+            streams.write_zlp();
+        }
+    }
+
+}
+
+
+
+/// AUDIO PROFILES
+/// Preset configurations applied by `AudioClassBuilder::profile()`. All
+/// profiles use S16LE streams on a single shared clock at the given rate, with
+/// implicit feedback on the input stream. Mono streams are located front
+/// center and stereo ones front left and right.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AudioProfile {
+    /// Mono `InMicrophone` input and stereo `OutHeadphones` output, category
+    /// `Headset`
+    Headset,
+    /// Stereo `OutSpeaker` output only, category `DesktopSpeaker`
+    Speaker,
+    /// Mono `InMicrophone` input only, category `Microphone`
+    Microphone,
+}
+
+
+
+/// AUDIO CLASS BUILDER
+/// Builder state before any stream has been added; `build()` is unavailable.
+pub struct NoStreams;
+
+/// Builder state once at least one stream has been added.
+pub struct WithStreams;
+
+pub struct AudioClassBuilder<'a, S = NoStreams> {
+    input: Option<StreamConfig<'a>>,
+    output: Option<StreamConfig<'a>>,
+    category: Option<Category>,
+    independent_clocks: bool,
+    status_interrupt: bool,
+    copy_protect: bool,
+    clock: ClockSource<'a>,
+    selectable_clocks: &'a [ClockSource<'a>],
+    selectable_inputs: &'a [TerminalType],
+    mixer_inputs: Option<&'a [TerminalType]>,
+    input_sink: Option<TerminalType>,
+    effect_unit: Option<EffectUnit>,
+    alt_setting_controls: bool,
+    cluster_control: bool,
+    extra_descriptor: Option<(u8, &'a [u8])>,
+    mute_control: bool,
+    volume_control: bool,
+    channel_mute_control: bool,
+    channel_volume_control: bool,
+    associate_terminals: bool,
+    clock_multiplier: Option<(u16, u16)>,
+    implicit_feedback: bool,
+    feedback_refresh: u8,
+    input_attributes: Option<EndpointAttributes>,
+    output_attributes: Option<EndpointAttributes>,
+    latency_control: bool,
+    input_zlp: bool,
+    discard_output: bool,
+    interface_order: InterfaceOrder,
+    strict_category: bool,
+    control_buffer: Option<usize>,
+    speed: UsbSpeed,
+    audio_1_0: bool,
+    marker: PhantomData<&'a u8>,
+    state: PhantomData<S>,
+}
+
+impl AudioClassBuilder<'static, NoStreams> {
+
+    pub fn new() -> AudioClassBuilder<'static, NoStreams> {
+        AudioClassBuilder {
+            input: None,
+            output: None,
+            category: None,
+            independent_clocks: false,
+            status_interrupt: false,
+            copy_protect: false,
+            clock: ClockSource::new(),
+            selectable_clocks: &[],
+            selectable_inputs: &[],
+            mixer_inputs: None,
+            input_sink: None,
+            effect_unit: None,
+            alt_setting_controls: false,
+            cluster_control: false,
+            extra_descriptor: None,
+            mute_control: false,
+            volume_control: false,
+            channel_mute_control: false,
+            channel_volume_control: false,
+            associate_terminals: false,
+            clock_multiplier: None,
+            implicit_feedback: true,
+            feedback_refresh: 3,
+            input_attributes: None,
+            output_attributes: None,
+            latency_control: false,
+            input_zlp: false,
+            discard_output: false,
+            interface_order: InterfaceOrder::ControlFirst,
+            strict_category: false,
+            control_buffer: None,
+            speed: UsbSpeed::Full,
+            audio_1_0: false,
+            marker: PhantomData,
+            state: PhantomData,
+        }
+    }
+
+}
+
+impl Default for AudioClassBuilder<'static, NoStreams> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, S> AudioClassBuilder<'a, S> {
+
+    pub fn input(self, input: StreamConfig<'a>) -> AudioClassBuilder<'a, WithStreams> {
+        AudioClassBuilder {
+            input: Some(input),
+            ..self.with_streams()
+        }
+    }
+
+    pub fn output(self, output: StreamConfig<'a>) -> AudioClassBuilder<'a, WithStreams> {
+        AudioClassBuilder {
+            output: Some(output),
+            ..self.with_streams()
+        }
+    }
+
+    /// Configure the streams, terminals and category for a common kind of
+    /// device in one call. See `AudioProfile` for what each profile sets; any
+    /// option can still be changed afterwards, and calling `input()` or
+    /// `output()` replaces the profile's stream in that direction. The streams
+    /// are checked as by `StreamConfig::new()`, returning its error for a rate
+    /// no stream can run at.
+    pub fn profile(self, profile: AudioProfile, rate: u32) -> Result<AudioClassBuilder<'a, WithStreams>> {
+        let builder = self.with_streams();
+
+        let mono_input = || {
+            StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, rate)
+                .map(|config| config.with_channel_config(ChannelConfig::mono()))
+        };
+        let stereo_output = |term_type| {
+            StreamConfig::new(Format::S16LE, 2, term_type, rate)
+                .map(|config| config.with_channel_config(ChannelConfig::stereo()))
+        };
+
+        Ok(
+            match profile {
+                AudioProfile::Headset => AudioClassBuilder {
+                    input: Some(mono_input()?),
+                    output: Some(stereo_output(TerminalType::OutHeadphones)?),
+                    category: Some(Category::Headset),
+                    ..builder
+                },
+                AudioProfile::Speaker => AudioClassBuilder {
+                    input: None,
+                    output: Some(stereo_output(TerminalType::OutSpeaker)?),
+                    category: Some(Category::DesktopSpeaker),
+                    ..builder
+                },
+                AudioProfile::Microphone => AudioClassBuilder {
+                    input: Some(mono_input()?),
+                    output: None,
+                    category: Some(Category::Microphone),
+                    ..builder
+                },
             }
+        )
+    }
+
+    fn with_streams(self) -> AudioClassBuilder<'a, WithStreams> {
+        AudioClassBuilder {
+            input: self.input,
+            output: self.output,
+            category: self.category,
+            independent_clocks: self.independent_clocks,
+            status_interrupt: self.status_interrupt,
+            copy_protect: self.copy_protect,
+            clock: self.clock,
+            selectable_clocks: self.selectable_clocks,
+            selectable_inputs: self.selectable_inputs,
+            mixer_inputs: self.mixer_inputs,
+            input_sink: self.input_sink,
+            effect_unit: self.effect_unit,
+            alt_setting_controls: self.alt_setting_controls,
+            cluster_control: self.cluster_control,
+            extra_descriptor: self.extra_descriptor,
+            mute_control: self.mute_control,
+            volume_control: self.volume_control,
+            channel_mute_control: self.channel_mute_control,
+            channel_volume_control: self.channel_volume_control,
+            associate_terminals: self.associate_terminals,
+            clock_multiplier: self.clock_multiplier,
+            implicit_feedback: self.implicit_feedback,
+            feedback_refresh: self.feedback_refresh,
+            input_attributes: self.input_attributes,
+            output_attributes: self.output_attributes,
+            latency_control: self.latency_control,
+            input_zlp: self.input_zlp,
+            discard_output: self.discard_output,
+            interface_order: self.interface_order,
+            strict_category: self.strict_category,
+            control_buffer: self.control_buffer,
+            speed: self.speed,
+            audio_1_0: self.audio_1_0,
+            marker: self.marker,
+            state: PhantomData,
         }
+    }
 
-        else if (
-            req.request_type == RequestType::Class
-                && req.recipient == Recipient::Interface
-                && (req.index >> 8) as u8 == ID_CLOCK_SRC
-                && (req.value >> 8) == 0x01 // clock freq control selector
-        ) {
+    /// Set the function category reported in the AudioControl header. This is
+    /// independent of the IAD function subclass. When not set, output-only
+    /// devices report `DesktopSpeaker` and all others report `IoBox`.
+    pub fn category(self, category: Category) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            category: Some(category),
+            ..self
+        }
+    }
 
-            let rate: [u8; 2] = R.to_be_bytes();
+    /// Give the input and output streams their own clock source entities
+    /// rather than sharing one, for duplex devices where capture and playback
+    /// run from different clocks. Each clock reports the rate of its stream.
+    /// Has no effect unless both streams are configured.
+    ///
+    /// Without independent clocks both streaming terminals are wired to the
+    /// one clock source, so `build()` returns `ClockRateMismatch` unless the
+    /// streams are built with the same rate.
+    pub fn independent_clocks(self, independent: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            independent_clocks: independent,
+            ..self
+        }
+    }
 
-            // range request
-            if (req.request == 0x02) {
-                match self.clock_index {
-                    0 => {
-                        xfer.accept_with(&[
-                            0x01, 0x00
-                        ]).ok();
-                        self.clock_index = 1;
-                        return;
-                    }
-                    _ => {
-                        xfer.accept_with(&[
-                            0x01, 0x00, // subranges
-                            rate[0], rate[1], 0x00, 0x00, // min
-                            rate[0], rate[1], 0x00, 0x00, // max
-                            0x01, 0x00, 0x00, 0x00  // res
-                        ]).ok();
-                        self.clock_index += 1;
-                        return;
-                    }
-                }
-            }
+    /// Add an interrupt IN endpoint to the AudioControl interface, used to
+    /// notify the host of control changes such as clock validity.
+    pub fn status_interrupt(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            status_interrupt: enabled,
+            ..self
+        }
+    }
 
-            // current value request
-            else if (req.request == 0x01) {
-                xfer.accept_with(&[
-                    rate[0], rate[1], 0x00, 0x00
-                ]).ok();
-                return;
-            }
+    /// Advertise a host programmable copy protect control on the output
+    /// terminal. The host sets the copy protection level of the content it
+    /// sends; see `AudioClass::copy_protect()`.
+    pub fn copy_protect(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            copy_protect: enabled,
+            ..self
+        }
+    }
 
+    /// Configure the clock sources as a whole: type, SOF synchronization,
+    /// frequency control, supported rates, rate policy and name. Replaces the
+    /// effect of any earlier `frequency_control()`, `sample_rates()`,
+    /// `rate_policy()` or `clock_sof_sync()`, which are shorthands for the
+    /// same settings. With `independent_clocks()` both clocks share it.
+    pub fn clock_source(self, clock: ClockSource<'a>) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            clock,
+            ..self
         }
     }
-    
-}
 
+    /// Offer the host a choice of clocks through a Clock Selector: the clock
+    /// of `clock_source()` on pin 1, then up to three more of `sources` on the
+    /// following pins, each a Clock Source entity of its own starting at the
+    /// same rate. The streams take their clock from the selector, except an
+    /// output with `independent_clocks()`, which keeps its own. Firmware
+    /// follows the choice through `AudioClass::selected_clock()`. `build()`
+    /// returns `TooManyClockSources` for more than three sources.
+    pub fn clock_selector(self, sources: &'a [ClockSource<'a>]) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            selectable_clocks: sources,
+            ..self
+        }
+    }
 
+    /// Offer the host a choice of sources for the input stream through a
+    /// Selector Unit: the input stream's terminal on pin 1, then up to three
+    /// more input terminals of `terminals` on the following pins, e.g. a line
+    /// input beside a microphone. Firmware routes the chosen source to the
+    /// stream, following `AudioClass::selected_input()`. Ignored without an
+    /// input stream; `build()` returns `TooManyInputTerminals` for more than
+    /// three terminals.
+    pub fn input_selector(self, terminals: &'a [TerminalType]) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            selectable_inputs: terminals,
+            ..self
+        }
+    }
 
-/// AUDIO CLASS BUILDER
-pub struct AudioClassBuilder<'a, const R: u16> {
-    input: Option<StreamConfig<'a, R>>,
-    output: Option<StreamConfig<'a, R>>,
-    marker: PhantomData<&'a u8>,
-}
+    /// Add a Mixer Unit to the input path, mixing the input stream's source
+    /// (its terminal, or the selector unit of `input_selector()`) with up to
+    /// three more input terminals of `terminals` into the stream's cluster.
+    /// The host sets the level of every input to output channel pair, which
+    /// firmware applies, reading them with `AudioClass::mixer_level()`; the
+    /// first input starts passed straight through and the others silent.
+    /// An empty `terminals` gives a one input mixer, remapping the terminal's
+    /// cluster to the stream's. Ignored without an input stream; `build()`
+    /// returns `TooManyInputTerminals` for more than three terminals, and
+    /// `MixerTooLarge` beyond 64 channel pairs.
+    pub fn mixer_unit(self, terminals: &'a [TerminalType]) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            mixer_inputs: Some(terminals),
+            ..self
+        }
+    }
 
-impl<'a, const R: u16> AudioClassBuilder<'a, R> {
+    /// Advertise the clock frequency control (the default) or leave it out.
+    /// Without it the host cannot query the sample rate and must infer it from
+    /// the stream, which breaks rate discovery on most hosts but works around a
+    /// few minimal ones that fail on the control. Frequency requests are then
+    /// ignored.
+    pub fn frequency_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            clock: self.clock.frequency_control(enabled),
+            ..self
+        }
+    }
 
-    pub fn new() -> AudioClassBuilder<'static, R> {
+    /// Add a second output terminal of the given (typically embedded or
+    /// external) type, fed from the input stream's input terminal alongside
+    /// the USB streaming terminal. This models a device that, for example,
+    /// records to local storage while also streaming to the host.
+    pub fn input_sink(self, sink: TerminalType) -> AudioClassBuilder<'a, S> {
         AudioClassBuilder {
-            input: None,
-            output: None,
-            marker: PhantomData,
+            input_sink: Some(sink),
+            ..self
         }
     }
 
-    pub fn input(self, input: StreamConfig<'a, R>) -> AudioClassBuilder<'a, R> {
+    /// Add an Effect Unit to the topology, such as a parametric EQ or reverb
+    /// the device applies in hardware. It is placed before the output terminal
+    /// of the output stream, or on the input stream when there is no output.
+    /// Class requests addressed to the unit are passed to its handler.
+    pub fn effect_unit(self, effect: EffectUnit) -> AudioClassBuilder<'a, S> {
         AudioClassBuilder {
-            input: Some(input),
-            output: self.output,
-            marker: self.marker,
+            effect_unit: Some(effect),
+            ..self
         }
     }
 
-    pub fn output(self, output: StreamConfig<'a, R>) -> AudioClassBuilder<'a, R> {
+    /// Offer the host a choice of discrete sample rates in Hz, in ascending
+    /// order as the RANGE response requires. The clocks advertise them all
+    /// and accept a SET_CUR to any of them, making the frequency control host
+    /// programmable; other rates are handled by the `rate_policy()`. Each
+    /// stream starts at its own configured rate, which should be one of the
+    /// list, and its endpoint is sized for the highest rate. Firmware follows
+    /// changes through `AudioClass::current_sample_rate()`.
+    pub fn sample_rates(self, rates: &'a [u32]) -> AudioClassBuilder<'a, S> {
         AudioClassBuilder {
-            input: self.input,
-            output: Some(output),
-            marker: self.marker,
+            clock: self.clock.rates(rates),
+            ..self
+        }
+    }
+
+    /// Choose how a host request to set an unsupported sample rate is handled,
+    /// `RatePolicy::Strict` by default. See `RatePolicy`.
+    pub fn rate_policy(self, policy: RatePolicy) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            clock: self.clock.rate_policy(policy),
+            ..self
+        }
+    }
+
+    /// Have `build()` check that the configuration descriptor fits a control
+    /// buffer of `size` bytes, failing with `DescriptorOverflow` before any
+    /// interface or endpoint is allocated. usb-device's buffer is 128 bytes,
+    /// or 256 with its `control-buffer-256` feature. See
+    /// `validate_descriptor_size()`.
+    pub fn control_buffer_size(self, size: usize) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            control_buffer: Some(size),
+            ..self
+        }
+    }
+
+    /// Describe the function as Audio Class 1.0 instead of 2.0, for hosts
+    /// that only support 1.0. The streams are described by their terminals
+    /// alone, each format with the discrete list of rates from
+    /// `sample_rates()`, and the host sets each stream's rate through its
+    /// endpoint, so a duplex device runs on independent clocks. Only one
+    /// format of those sharing an alternate setting is offered. Clock
+    /// selectors and multipliers, units, the status interrupt and the
+    /// copy protect, cluster, latency and alternate setting controls have no
+    /// 1.0 equivalent, nor do high speed and intervals other than 1; `build()`
+    /// returns `UnsupportedInAudio10` if any is configured.
+    pub fn audio_1_0(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            audio_1_0: enabled,
+            ..self
+        }
+    }
+
+    /// Size the endpoints for a high speed bus, where each packet covers a
+    /// 125 us microframe rather than a 1 ms frame, and send feedback in the
+    /// 4 byte 16.16 format. Off by default. usb-device does not report the
+    /// negotiated speed, so this must match what the controller will run at.
+    /// `StreamConfig::packet_size()` remains that of full speed; `build()`
+    /// checks the endpoints against the budget of the speed chosen here.
+    pub fn high_speed(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            speed: if enabled { UsbSpeed::High } else { UsbSpeed::Full },
+            ..self
+        }
+    }
+
+    /// Choose the order of the interfaces in the configuration descriptor,
+    /// `InterfaceOrder::ControlFirst` by default. `StreamingFirst` breaks the
+    /// standard layout and should only be used for a specific host that
+    /// needs it. See `InterfaceOrder`.
+    pub fn interface_order(self, order: InterfaceOrder) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            interface_order: order,
+            ..self
+        }
+    }
+
+    /// Advertise the read only active and valid alternate setting controls on
+    /// each streaming interface, letting the host read which alternate
+    /// settings exist instead of probing them with SET_INTERFACE.
+    pub fn alt_setting_controls(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            alt_setting_controls: enabled,
+            ..self
+        }
+    }
+
+    /// Advertise a read only cluster control on the output stream's input
+    /// terminal, starting at the stream's channel count. Firmware then updates
+    /// it with `AudioClass::set_output_cluster()`.
+    pub fn cluster_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            cluster_control: enabled,
+            ..self
+        }
+    }
+
+    /// Add a host programmable master mute control. Together with
+    /// `volume_control()` this places a Feature Unit in the topology, on the
+    /// same path as an effect unit and ahead of it. Mute and volume are
+    /// advertised separately, so hosts that only show a mute switch for a
+    /// distinct mute control find one.
+    pub fn mute_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            mute_control: enabled,
+            ..self
+        }
+    }
+
+    /// Add a host programmable master volume control, from -60 dB to 0 dB in
+    /// 1 dB steps and starting at 0 dB. See `mute_control()`.
+    pub fn volume_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            volume_control: enabled,
+            ..self
+        }
+    }
+
+    /// Add a host programmable mute control to each logical channel, up to
+    /// 32, alongside the master mute of `mute_control()`. The master mute
+    /// still silences every channel; see `AudioClass::channel_muted()`.
+    pub fn channel_mute_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            channel_mute_control: enabled,
+            ..self
+        }
+    }
+
+    /// Add a host programmable volume control to each logical channel, up to
+    /// 32, with the same range as the master volume of `volume_control()`,
+    /// for independent left and right levels. The master volume applies on
+    /// top; see `AudioClass::channel_volume()`.
+    pub fn channel_volume_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            channel_volume_control: enabled,
+            ..self
+        }
+    }
+
+    /// Link the input stream's input terminal and the output stream's output
+    /// terminal through their associated terminal fields, telling the host
+    /// that they are acoustically coupled, as the microphone and speaker of a
+    /// headset are, for echo cancellation. Has no effect unless both streams
+    /// are configured.
+    pub fn associate_terminals(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            associate_terminals: enabled,
+            ..self
+        }
+    }
+
+    /// Fail `build()` with `CategoryMismatch` when the function category does
+    /// not fit the configured terminals, as checked by `validate_category()`.
+    /// Off by default, as hosts only use the combination as a hint.
+    pub fn strict_category(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            strict_category: enabled,
+            ..self
+        }
+    }
+
+    /// Advertise the read only latency control in the AudioControl header,
+    /// through which the host reads the latency of the whole function for
+    /// A/V sync. Firmware reports it with `AudioClass::set_function_latency()`;
+    /// it reads 0 until then.
+    pub fn latency_control(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            latency_control: enabled,
+            ..self
         }
     }
 
-    pub fn build<B: UsbBus>(self, allocator: &'a UsbBusAllocator<B>) -> Result<AudioClass<'a, B, R>> {
+    /// Describe a clock derived from the first clock source by the ratio
+    /// `numerator / denominator`, such as a codec master clock. The host can
+    /// read both through read only controls to compute the derived rate; the
+    /// audio terminals keep running from the clock source itself.
+    pub fn clock_multiplier(self, numerator: u16, denominator: u16) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            clock_multiplier: Some((numerator, denominator)),
+            ..self
+        }
+    }
+
+    /// Declare the input stream's data endpoint as implicit feedback data (the
+    /// default) or as plain asynchronous data. Capture needs no feedback
+    /// endpoint of its own: the host follows an asynchronous IN endpoint's
+    /// rate from the packet sizes it sends. Implicit feedback additionally
+    /// lets the host pace the output stream from those sizes, which suits
+    /// duplex devices with a shared clock. Turn it off when the streams run
+    /// from independent clocks, so only the output's explicit feedback
+    /// endpoint is used.
+    pub fn implicit_feedback(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            implicit_feedback: enabled,
+            ..self
+        }
+    }
+
+    /// Have the host poll the output stream's feedback endpoint every
+    /// 2^`exponent` ms, from 1 (2 ms) to 9 (512 ms), the range allowed for
+    /// feedback endpoints. Defaults to 3, every 8 ms. Written as the
+    /// endpoint's bInterval, or as its bRefresh with `audio_1_0()`; `build()`
+    /// returns `InvalidInterval` outside the range.
+    pub fn feedback_refresh(self, exponent: u8) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            feedback_refresh: exponent,
+            ..self
+        }
+    }
+
+    /// Keep the input stream scheduled when firmware has no data: each
+    /// `UsbDevice::poll` that finds the stream open and no packet pending
+    /// queues a zero-length packet on the input endpoint. Buses that treat
+    /// an isochronous IN token finding no data as an incomplete transfer and
+    /// stop servicing the endpoint need it, as reported for some Synopsys OTG
+    /// based controllers; buses that answer such tokens with a ZLP in
+    /// hardware do not. A `write()` racing the poll may see `WouldBlock` for
+    /// that frame. Off by default.
+    pub fn input_zlp(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            input_zlp: enabled,
+            ..self
+        }
+    }
+
+    /// Drain and discard every packet the host sends on the output stream,
+    /// from within `UsbDevice::poll`, so the device plays back without a
+    /// codec and the host sees no stalled stream. A development aid for
+    /// bring-up only: `read()` gets no data while it is enabled.
+    pub fn discard_output(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            discard_output: enabled,
+            ..self
+        }
+    }
+
+    /// Set all attributes of the input stream's data endpoint at once,
+    /// overriding `implicit_feedback()`. Asynchronous implicit feedback data
+    /// by default.
+    pub fn input_endpoint_attributes(self, attributes: EndpointAttributes) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            input_attributes: Some(attributes),
+            ..self
+        }
+    }
+
+    /// Set all attributes of the output stream's data endpoint at once.
+    /// Asynchronous data by default, paced by the feedback endpoint. Only an
+    /// asynchronous output gets a feedback endpoint; an adaptive or
+    /// synchronous one follows the host's rate instead.
+    pub fn output_endpoint_attributes(self, attributes: EndpointAttributes) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            output_attributes: Some(attributes),
+            ..self
+        }
+    }
+
+    /// Report the clock sources as synchronized to the USB Start Of Frame, for
+    /// devices that derive their sample clock from SOF. The host may then
+    /// treat the stream as locked to the bus, as for synchronous endpoints;
+    /// leave it off (the default) for a free running clock on asynchronous
+    /// endpoints, whose drift the feedback endpoint reports instead.
+    pub fn clock_sof_sync(self, enabled: bool) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            clock: self.clock.sof_sync(enabled),
+            ..self
+        }
+    }
+
+    /// Append a descriptor, e.g. a vendor-specific one for a companion host
+    /// driver, after all the audio descriptors. `data` is the descriptor body;
+    /// bLength and `descriptor_type` are prepended. It is counted in the
+    /// configuration's wTotalLength but lies outside the AudioControl header's.
+    pub fn extra_descriptor(self, descriptor_type: u8, data: &'a [u8]) -> AudioClassBuilder<'a, S> {
+        AudioClassBuilder {
+            extra_descriptor: Some((descriptor_type, data)),
+            ..self
+        }
+    }
+
+}
+
+impl<'a> AudioClassBuilder<'a, WithStreams> {
+
+    /// Check that the isochronous endpoints the class would allocate, data and
+    /// feedback, fit together in one frame (full speed) or microframe (high
+    /// speed). Packets are sized for one transfer per interval, so the sum of
+    /// their maximum sizes must not exceed the budget of the given speed.
+    pub fn validate_bandwidth(&self, speed: UsbSpeed) -> Result<()> {
+        let feedback: u16 = if self.output_feedback() { feedback::packet_size(speed) } else { 0 };
+        let input: u16 = self.input.as_ref().map_or(0, |config| config.packet_size_for(self.clock.rates, speed));
+        let output: u16 = self.output.as_ref().map_or(0, |config| config.packet_size_for(self.clock.rates, speed).saturating_add(feedback));
+
+        let required = input.saturating_add(output);
+        let available = speed.iso_budget();
+
+        if required > available {
+            return Err(Error::BandwidthExceeded { required, available });
+        }
+
+        Ok(())
+    }
+
+    // the output stream gets a feedback endpoint when it is asynchronous
+    fn output_feedback(&self) -> bool {
+        self.output_attributes.is_none_or(|attributes| attributes.sync == Asynchronous)
+    }
+
+    /// Check that the function category is consistent with the terminals,
+    /// since hosts infer the device's purpose from the combination: a
+    /// `Headset` needs a microphone input and a headphone output, a
+    /// `Microphone` a microphone input only, and a `DesktopSpeaker` a speaker
+    /// output only. Other categories accept any terminals. Returns
+    /// `CategoryMismatch` otherwise.
+    pub fn validate_category(&self) -> Result<()> {
+        let input = self.input.as_ref().map(|config| config.term_type);
+        let output = self.output.as_ref().map(|config| config.term_type);
+
+        // terminal type ranges of the Terminal Types spec
+        let microphone = |t: TerminalType| t.code() & 0xFF00 == 0x0200;
+        let speaker = |t: TerminalType| t.code() & 0xFF00 == 0x0300;
+        let headphones = |t: TerminalType| [TerminalType::OutHeadphones, TerminalType::OutHeadMountedDisplayAudio].iter().any(|h| h.code() == t.code());
+
+        let consistent = match self.effective_category() {
+            Category::Headset => input.is_some_and(microphone) && output.is_some_and(headphones),
+            Category::Microphone => input.is_some_and(microphone) && output.is_none(),
+            Category::DesktopSpeaker => input.is_none() && output.is_some_and(speaker),
+            _ => true,
+        };
+
+        if !consistent {
+            return Err(Error::CategoryMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the configuration descriptor, made of its 9 byte header
+    /// and this function, fits a control buffer of `available` bytes. usb-
+    /// device assembles the whole configuration descriptor in its control
+    /// buffer, so one that does not fit fails GET_DESCRIPTOR and the device
+    /// never enumerates. Returns `DescriptorOverflow` with both sizes, for
+    /// firmware to fall back to a smaller configuration. Other classes of a
+    /// composite device need room of their own.
+    pub fn validate_descriptor_size(&self, available: usize) -> Result<()> {
+        let required = 9 + self.layout().config_length();
+
+        if required > available {
+            return Err(Error::DescriptorOverflow { required, available });
+        }
+
+        Ok(())
+    }
+
+    fn layout(&self) -> Layout {
+        Layout {
+            n_clocks: (self.input.is_some() || self.output.is_some()) as u8
+                + (self.separate_clocks() && self.input.is_some() && self.output.is_some()) as u8
+                + self.selectable_clocks.len() as u8,
+            selector_pins: if self.selectable_clocks.is_empty() { 0 } else { 1 + self.selectable_clocks.len() as u8 },
+            input_pins: if self.input.is_none() || self.selectable_inputs.is_empty() { 0 } else { 1 + self.selectable_inputs.len() as u8 },
+            mixer_pins: self.mixer().map_or(0, |mixer| mixer.n_pins()),
+            mixer_controls: self.mixer().map_or(0, |mixer| mixer.n_controls()),
+            multiplier: self.clock_multiplier.is_some(),
+            input: self.input.is_some(),
+            input_sink: self.input_sink.is_some(),
+            output: self.output.is_some(),
+            output_feedback: self.output_feedback(),
+            feature_unit: self.mute_control || self.volume_control || self.channel_mute_control || self.channel_volume_control,
+            effect_unit: self.effect_unit.is_some(),
+            unit_channels: self.output.as_ref().or(self.input.as_ref()).map_or(0, |config| config.n_channels),
+            status_endpoint: self.status_interrupt,
+            input_alts: self.input.as_ref().map_or(0, StreamConfig::alt_settings),
+            output_alts: self.output.as_ref().map_or(0, StreamConfig::alt_settings),
+            extra_descriptor: self.extra_descriptor.map(|(_, data)| data.len()),
+            audio_1_0: self.audio_1_0,
+            n_rates: self.clock.rates.len().max(1),
+        }
+    }
+
+    // the mixer unit the input stream would get, before its size is checked
+    fn mixer(&self) -> Option<MixerUnit<'a>> {
+        let input = self.input.as_ref()?;
+        let pin_channels = input.terminal_cluster.map_or(input.n_channels, |cluster| cluster.n_channels);
+
+        self.mixer_inputs.map(|terminals| MixerUnit::new(terminals, pin_channels, input.n_channels))
+    }
+
+    // each 1.0 stream has its rate set through its own endpoint
+    fn separate_clocks(&self) -> bool {
+        self.independent_clocks || self.audio_1_0
+    }
+
+    fn check_audio_1_0(&self) -> Result<()> {
+        let units = self.effect_unit.is_some()
+            || !self.selectable_inputs.is_empty()
+            || self.mixer_inputs.is_some()
+            || self.mute_control
+            || self.volume_control
+            || self.channel_mute_control
+            || self.channel_volume_control;
+
+        let controls = self.copy_protect
+            || self.cluster_control
+            || self.latency_control
+            || self.alt_setting_controls
+            || self.status_interrupt;
+
+        let clocks = !self.selectable_clocks.is_empty() || self.clock_multiplier.is_some();
+
+        let intervals = [&self.input, &self.output].into_iter().flatten().any(|config| config.interval != 1);
+
+        if units || controls || clocks || intervals || self.speed == UsbSpeed::High {
+            return Err(Error::UnsupportedInAudio10);
+        }
+
+        Ok(())
+    }
+
+    fn effective_category(&self) -> Category {
+        self.category.unwrap_or(
+            if self.input.is_none() && self.output.is_some() {
+                Category::DesktopSpeaker
+            } else {
+                Category::IoBox
+            }
+        )
+    }
+
+    /// Allocate the interfaces and endpoints and assemble the class. Only
+    /// available once at least one stream has been added. Building consumes
+    /// the builder, so the same configuration cannot allocate from the
+    /// allocator twice; a second class needs a builder of its own.
+    ///
+    /// Returns `BandwidthExceeded` if the endpoints do not fit the speed set
+    /// by `high_speed()`, see `validate_bandwidth()`, and `UsbError` if the
+    /// bus runs out of endpoints, e.g. with other classes sharing the device.
+    /// Interfaces and endpoints allocated before the failure stay taken.
+    ///
+    /// A builder cannot be built twice:
+    ///
+    /// ```compile_fail
+    /// # use usb_device::bus::{UsbBus, UsbBusAllocator};
+    /// # use usbd_audio_2::{AudioClassBuilder, Format, StreamConfig, TerminalType};
+    /// fn build_twice<B: UsbBus>(allocator: &UsbBusAllocator<B>) {
+    ///     let builder = AudioClassBuilder::new()
+    ///         .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap());
+    ///     let first = builder.build(allocator);
+    ///     let second = builder.build(allocator); // use of moved value
+    /// }
+    /// ```
+    pub fn build<B: UsbBus>(self, allocator: &'a UsbBusAllocator<B>) -> Result<AudioClass<'a, B>> {
+
+        if self.strict_category {
+            self.validate_category()?;
+        }
+
+        if self.selectable_clocks.len() > MAX_SELECTABLE_CLOCKS {
+            return Err(Error::TooManyClockSources);
+        }
+
+        if self.selectable_inputs.len() > MAX_SELECTABLE_INPUTS || self.mixer_inputs.is_some_and(|inputs| inputs.len() > MAX_MIXER_INPUTS) {
+            return Err(Error::TooManyInputTerminals);
+        }
+
+        if self.mixer().is_some_and(|mixer| mixer.n_controls() > MAX_MIXER_CONTROLS) {
+            return Err(Error::MixerTooLarge);
+        }
+
+        if self.audio_1_0 {
+            self.check_audio_1_0()?;
+        }
+
+        if !(1..=9).contains(&self.feedback_refresh) {
+            return Err(Error::InvalidInterval);
+        }
+
+        self.validate_bandwidth(self.speed)?;
+
+        // a shared clock runs both streams at one rate
+        if let (Some(input), Some(output)) = (&self.input, &self.output) {
+            if !self.separate_clocks() && input.rate != output.rate {
+                return Err(Error::ClockRateMismatch);
+            }
+        }
+
+        if let Some(available) = self.control_buffer {
+            self.validate_descriptor_size(available)?;
+        }
+
+        let layout_length = self.layout().config_length();
+        let separate_clocks = self.separate_clocks();
+
+        // streams on the first clock are wired through the selector if any
+        let clock_id = if self.selectable_clocks.is_empty() { ID_CLOCK_SRC } else { ID_CLOCK_SELECTOR };
+
+        let category = self.effective_category();
 
         let mut ac = AudioClass {
-            control_interface: allocator.interface(),
-            input: None,
-            output: None,
-            clock_index: 0,
+            streams: AudioStreams {
+                input: None,
+                output: None,
+            },
+            control: ControlState {
+                control_interface: allocator.interface(),
+                clocks: Default::default(),
+                clock_selector: if self.selectable_clocks.is_empty() {
+                    None
+                } else {
+                    Some(ClockSelector {
+                        n_pins: 1 + self.selectable_clocks.len() as u8,
+                        selected: 1,
+                    })
+                },
+                input_selector: if self.input.is_none() || self.selectable_inputs.is_empty() {
+                    None
+                } else {
+                    Some(SelectorUnit {
+                        terminals: self.selectable_inputs,
+                        selected: 1,
+                    })
+                },
+                mixer_unit: self.mixer(),
+                last_control: None,
+                stream_changes: 0,
+                category,
+                status_endpoint: None,
+                status_queue: StatusQueue::new(),
+                configured: false,
+                input_sink: self.input_sink,
+                effect_unit: self.effect_unit,
+                alt_setting_controls: self.alt_setting_controls,
+                cluster: match self.output {
+                    Some(ref output) if self.cluster_control => Some(Cluster {
+                        n_channels: output.n_channels,
+                        channel_config: output.channel_config.bits(),
+                    }),
+                    _ => None,
+                },
+                extra_descriptor: self.extra_descriptor,
+                feature_unit: if self.mute_control || self.volume_control || self.channel_mute_control || self.channel_volume_control {
+                    Some(FeatureUnit {
+                        mute: if self.mute_control { Some(false) } else { None },
+                        volume: if self.volume_control { Some(VOLUME_MAX) } else { None },
+                        channel_mute: if self.channel_mute_control { Some(0) } else { None },
+                        channel_volume: if self.channel_volume_control { Some([VOLUME_MAX; FeatureUnit::MAX_CHANNELS as usize]) } else { None },
+                        // the units go on the output path when there is one
+                        n_channels: self.output.as_ref().or(self.input.as_ref()).map_or(0, |config| config.n_channels),
+                    })
+                } else {
+                    None
+                },
+                associate_terminals: self.associate_terminals,
+                clock_multiplier: self.clock_multiplier.map(|(numerator, denominator)| ClockMultiplier {
+                    numerator,
+                    denominator,
+                }),
+                function_latency: if self.latency_control { Some(0) } else { None },
+                input_zlp: self.input_zlp,
+                discard_output: self.discard_output,
+                interface_order: self.interface_order,
+                audio_1_0: self.audio_1_0,
+                feedback_refresh: self.feedback_refresh,
+                copy_protect: if self.copy_protect && self.output.is_some() {
+                    Some(CopyProtect::Cpl0)
+                } else {
+                    None
+                },
+            },
         };
 
+        if self.status_interrupt {
+            ac.control.status_endpoint = Some(allocator.alloc(None, EndpointType::Interrupt, STATUS_PACKET_SIZE, 1).map_err(Error::UsbError)?);
+        }
+
         if let Some(input_config) = self.input {
 
             let input_interface = allocator.interface();
 
+            let input_attributes = self.input_attributes.unwrap_or(EndpointAttributes {
+                sync: Asynchronous,
+                usage: if self.implicit_feedback { ImplicitFeedbackData } else { Data },
+                max_packets_only: false,
+            });
+
             let input_endpoint = allocator.alloc(
                 None,
-                EndpointType::Isochronous {
-                    synchronization: Asynchronous,
-                    usage: ImplicitFeedbackData,
-                },
-                input_config.packet_size(),
-                1
-            ).unwrap();
+                input_attributes.ep_type(),
+                input_config.packet_size_for(self.clock.rates, self.speed),
+                input_config.interval
+            ).map_err(Error::UsbError)?;
+
+            ac.control.clocks[0] = Some(Clock::new(ID_CLOCK_SRC, input_config.rate, self.clock, self.clock.name.map(|_| allocator.string())));
 
-            ac.input = Some(
+            let terminal_name = input_config.terminal_name.map(|_| allocator.string());
+            let channel_names = input_config.alloc_channel_names(allocator);
+
+            ac.streams.input = Some(
                 AudioStream {
+                    // read before the config moves in
+                    rate: AtomicU32::new(input_config.rate),
                     stream_config: input_config,
                     interface: input_interface,
                     endpoint: input_endpoint,
-                    alt_setting: DEFAULT_ALTERNATE_SETTING,
+                    alt_setting: AtomicU8::new(DEFAULT_ALTERNATE_SETTING),
+                    clock_id,
+                    feedback: None,
+                    last_feedback: AtomicU32::new(0),
+                    in_flight: AtomicBool::new(false),
+                    received: AtomicBool::new(false),
+                    attributes: input_attributes,
+                    speed: self.speed,
+                    terminal_name,
+                    channel_names,
                 }
             )
         }
@@ -619,26 +4805,76 @@ impl<'a, const R: u16> AudioClassBuilder<'a, R> {
 
             let output_interface = allocator.interface();
 
+            let output_attributes = self.output_attributes.unwrap_or(EndpointAttributes {
+                sync: Asynchronous,
+                usage: Data,
+                max_packets_only: false,
+            });
+
             let output_endpoint = allocator.alloc(
+                None,
+                output_attributes.ep_type(),
+                output_config.packet_size_for(self.clock.rates, self.speed),
+                output_config.interval
+            ).map_err(Error::UsbError)?;
+
+            // only an asynchronous sink runs from a clock of its own that the
+            // host has to be told about
+            let feedback_endpoint = (output_attributes.sync == Asynchronous).then(|| allocator.alloc(
                 None,
                 EndpointType::Isochronous {
-                    synchronization: Asynchronous,
-                    usage: Data,
+                    synchronization: NoSynchronization,
+                    usage: Feedback,
                 },
-                output_config.packet_size(),
-                1
-            ).unwrap();
+                feedback::packet_size(self.speed),
+                // 1.0 polls every frame, refreshing per bRefresh instead
+                if self.audio_1_0 { 1 } else { feedback::interval(self.feedback_refresh, self.speed) }
+            ).map_err(Error::UsbError)).transpose()?;
+
+            // the input stream's clock when shared, its rate checked equal
+            let output_clock_id = if ac.control.clocks[0].is_none() {
+                ac.control.clocks[0] = Some(Clock::new(ID_CLOCK_SRC, output_config.rate, self.clock, self.clock.name.map(|_| allocator.string())));
+                clock_id
+            } else if separate_clocks {
+                ac.control.clocks[1] = Some(Clock::new(ID_OUTPUT_CLOCK_SRC, output_config.rate, self.clock, self.clock.name.map(|_| allocator.string())));
+                ID_OUTPUT_CLOCK_SRC
+            } else {
+                clock_id
+            };
+
+            let terminal_name = output_config.terminal_name.map(|_| allocator.string());
+            let channel_names = output_config.alloc_channel_names(allocator);
 
-            ac.output = Some(
+            ac.streams.output = Some(
                 AudioStream {
+                    // read before the config moves in
+                    rate: AtomicU32::new(output_config.rate),
                     stream_config: output_config,
                     interface: output_interface,
                     endpoint: output_endpoint,
-                    alt_setting: DEFAULT_ALTERNATE_SETTING,
+                    alt_setting: AtomicU8::new(DEFAULT_ALTERNATE_SETTING),
+                    clock_id: output_clock_id,
+                    feedback: feedback_endpoint,
+                    last_feedback: AtomicU32::new(0),
+                    in_flight: AtomicBool::new(false),
+                    received: AtomicBool::new(false),
+                    attributes: output_attributes,
+                    speed: self.speed,
+                    terminal_name,
+                    channel_names,
                 }
             )
         }
 
+        // the selectable clocks start at the rate of the first
+        let rate = ac.control.clocks[0].as_ref().map_or(0, |clock| clock.rate);
+
+        for ((slot, &source), id) in ac.control.clocks[2..].iter_mut().zip(self.selectable_clocks).zip(ID_SELECTABLE_CLOCK_SRC) {
+            *slot = Some(Clock::new(id, rate, source, source.name.map(|_| allocator.string())));
+        }
+
+        debug_assert_eq!(layout_length, ac.config_contribution_len(), "builder and class descriptor layouts differ");
+
         Ok(ac)
     }
 
@@ -0,0 +1,88 @@
+//! Packet pacing for rates that are not a whole number of samples per frame
+//!
+
+/// Yields the number of samples to send in each 1 ms frame so that the average
+/// over time matches the sample rate exactly. At 44.1 kHz this produces nine
+/// packets of 44 samples followed by one of 45.
+#[derive(Clone, Copy, Debug)]
+pub struct FramePacer {
+    rate: u32,
+    frames: u32,
+    remainder: u32,
+}
+
+impl FramePacer {
+
+    pub fn new(rate: u32) -> FramePacer {
+        FramePacer {
+            rate,
+            frames: 1000,
+            remainder: 0,
+        }
+    }
+
+    /// Pace packets per 125 us microframe, for a class built with
+    /// `AudioClassBuilder::high_speed()`
+    pub fn high_speed(rate: u32) -> FramePacer {
+        FramePacer {
+            rate,
+            frames: 8000,
+            remainder: 0,
+        }
+    }
+
+    /// Sample count for the next frame's packet
+    pub fn next_samples(&mut self) -> u32 {
+        // accumulate the fractional sample each frame leaves over
+        self.remainder += self.rate % self.frames;
+
+        if self.remainder >= self.frames {
+            self.remainder -= self.frames;
+            self.rate / self.frames + 1
+        } else {
+            self.rate / self.frames
+        }
+    }
+
+    /// Restart the pattern, e.g. when the stream is reopened
+    pub fn reset(&mut self) {
+        self.remainder = 0;
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn averages_the_rate_over_a_second() {
+        for rate in [8000, 22050, 44100, 48000, 88200, 96000] {
+            let mut pacer = FramePacer::new(rate);
+            let samples: u32 = (0..1000).map(|_| pacer.next_samples()).sum();
+
+            assert_eq!(samples, rate);
+        }
+    }
+
+    #[test]
+    fn paces_44_1_khz_as_nine_44s_then_a_45() {
+        let mut pacer = FramePacer::new(44100);
+        let packets: [u32; 10] = core::array::from_fn(|_| pacer.next_samples());
+
+        assert_eq!(packets, [44, 44, 44, 44, 44, 44, 44, 44, 44, 45]);
+    }
+
+    #[test]
+    fn high_speed_averages_the_rate_per_microframe() {
+        for rate in [44100, 48000, 192000] {
+            let mut pacer = FramePacer::high_speed(rate);
+            let samples: u32 = (0..8000).map(|_| pacer.next_samples()).sum();
+
+            assert_eq!(samples, rate);
+        }
+    }
+
+}
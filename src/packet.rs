@@ -0,0 +1,103 @@
+//! Format-aware read access to the samples of a received packet
+//!
+
+use crate::Format;
+
+/// Borrows a packet as returned by `read()` and decodes individual samples in
+/// place. Samples are interleaved by channel within each frame, as carried by
+/// a Type I stream.
+#[derive(Clone, Copy, Debug)]
+pub struct PacketView<'p> {
+    data: &'p [u8],
+    format: Format,
+    n_channels: u8,
+}
+
+impl<'p> PacketView<'p> {
+
+    pub fn new(data: &'p [u8], format: Format, n_channels: u8) -> PacketView<'p> {
+        PacketView {
+            data,
+            format,
+            n_channels,
+        }
+    }
+
+    /// Number of complete frames (one sample per channel) in the packet
+    pub fn frames(&self) -> usize {
+        let frame_size = self.format.size() as usize * self.n_channels as usize;
+
+        self.data.len().checked_div(frame_size).unwrap_or(0)
+    }
+
+    /// Decode the sample of `channel`, counted from 0, in `frame`, sign
+    /// extended to i32 without rescaling. `U8` samples are offset by -128 to
+    /// center them on 0, and `F32LE` samples are returned as their raw bits,
+    /// for `f32::from_bits`. Returns None when out of range.
+    pub fn sample(&self, frame: usize, channel: u8) -> Option<i32> {
+        if channel >= self.n_channels || frame >= self.frames() {
+            return None;
+        }
+
+        let size = self.format.size() as usize;
+        let offset = (frame * self.n_channels as usize + channel as usize) * size;
+        let bytes = &self.data[offset..offset + size];
+
+        Some(match self.format {
+            Format::U8 => bytes[0] as i32 - 128,
+            Format::S16LE => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+            // shift the top byte into the sign bit and back to extend it
+            Format::S24LE => i32::from_le_bytes([0x00, bytes[0], bytes[1], bytes[2]]) >> 8,
+            // the valid bits are in the upper three bytes, the lowest is padding
+            Format::S24In32LE => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) >> 8,
+            Format::S32LE | Format::F32LE => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        })
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn indexes_s16le_frames() {
+        // two stereo frames: (1, -1), (i16::MIN, i16::MAX)
+        let data = [0x01, 0x00, 0xFF, 0xFF, 0x00, 0x80, 0xFF, 0x7F];
+        let view = PacketView::new(&data, Format::S16LE, 2);
+
+        assert_eq!(view.frames(), 2);
+        assert_eq!(view.sample(0, 0), Some(1));
+        assert_eq!(view.sample(0, 1), Some(-1));
+        assert_eq!(view.sample(1, 0), Some(-32768));
+        assert_eq!(view.sample(1, 1), Some(32767));
+        assert_eq!(view.sample(2, 0), None);
+        assert_eq!(view.sample(0, 2), None);
+    }
+
+    #[test]
+    fn sign_extends_s24le() {
+        // three mono frames: -1, the most negative and the most positive
+        let data = [0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x80, 0xFF, 0xFF, 0x7F];
+        let view = PacketView::new(&data, Format::S24LE, 1);
+
+        assert_eq!(view.frames(), 3);
+        assert_eq!(view.sample(0, 0), Some(-1));
+        assert_eq!(view.sample(1, 0), Some(-0x80_0000));
+        assert_eq!(view.sample(2, 0), Some(0x7F_FFFF));
+    }
+
+    #[test]
+    fn ignores_a_partial_frame() {
+        // one stereo S24LE frame and the first sample of the next
+        let data = [0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x00];
+        let view = PacketView::new(&data, Format::S24LE, 2);
+
+        assert_eq!(view.frames(), 1);
+        assert_eq!(view.sample(0, 1), Some(2));
+        assert_eq!(view.sample(1, 0), None);
+    }
+
+}
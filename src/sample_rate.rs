@@ -0,0 +1,98 @@
+//! Sample rates as carried by the 4 byte clock frequency control
+//!
+
+/// Common sample rates, with `Custom` for any other rate in Hz
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SampleRate {
+    Hz8000,
+    Hz16000,
+    Hz32000,
+    Hz44100,
+    Hz48000,
+    Hz88200,
+    Hz96000,
+    Hz176400,
+    Hz192000,
+    Custom(u32),
+}
+
+impl SampleRate {
+
+    pub fn from_hz(hz: u32) -> SampleRate {
+        match hz {
+            8000 => SampleRate::Hz8000,
+            16000 => SampleRate::Hz16000,
+            32000 => SampleRate::Hz32000,
+            44100 => SampleRate::Hz44100,
+            48000 => SampleRate::Hz48000,
+            88200 => SampleRate::Hz88200,
+            96000 => SampleRate::Hz96000,
+            176400 => SampleRate::Hz176400,
+            192000 => SampleRate::Hz192000,
+            hz => SampleRate::Custom(hz),
+        }
+    }
+
+    pub fn hz(&self) -> u32 {
+        match self {
+            SampleRate::Hz8000 => 8000,
+            SampleRate::Hz16000 => 16000,
+            SampleRate::Hz32000 => 32000,
+            SampleRate::Hz44100 => 44100,
+            SampleRate::Hz48000 => 48000,
+            SampleRate::Hz88200 => 88200,
+            SampleRate::Hz96000 => 96000,
+            SampleRate::Hz176400 => 176400,
+            SampleRate::Hz192000 => 192000,
+            SampleRate::Custom(hz) => *hz,
+        }
+    }
+
+    /// Decode the little-endian dCUR of a clock frequency request
+    pub fn from_bytes(bytes: [u8; 4]) -> SampleRate {
+        SampleRate::from_hz(u32::from_le_bytes(bytes))
+    }
+
+    /// Encode as the little-endian dCUR of a clock frequency request
+    pub fn to_bytes(&self) -> [u8; 4] {
+        self.hz().to_le_bytes()
+    }
+
+}
+
+impl From<u32> for SampleRate {
+    fn from(hz: u32) -> SampleRate {
+        SampleRate::from_hz(hz)
+    }
+}
+
+impl From<SampleRate> for u32 {
+    fn from(rate: SampleRate) -> u32 {
+        rate.hz()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_wire_value() {
+        for hz in [8000, 44100, 48000, 192000, 12345, 0, u32::MAX] {
+            let rate = SampleRate::from_hz(hz);
+
+            assert_eq!(rate.to_bytes(), hz.to_le_bytes());
+            assert_eq!(SampleRate::from_bytes(rate.to_bytes()), rate);
+        }
+    }
+
+    #[test]
+    fn names_the_common_rates() {
+        assert_eq!(SampleRate::from_bytes([0x44, 0xAC, 0x00, 0x00]), SampleRate::Hz44100);
+        assert_eq!(SampleRate::from_bytes([0x80, 0xBB, 0x00, 0x00]), SampleRate::Hz48000);
+        assert_eq!(SampleRate::from_bytes([0x39, 0x30, 0x00, 0x00]), SampleRate::Custom(12345));
+    }
+
+}
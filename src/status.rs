@@ -0,0 +1,67 @@
+//! AudioControl status interrupt messages as defined in Universal Serial Bus
+//! Device Class Definition for Audio Devices, Release 2.0, Section 6.1
+//!
+
+use usb_device::{
+    class_prelude::*,
+    endpoint::{Endpoint, In},
+};
+
+use crate::class_codes::CUR;
+
+pub const STATUS_PACKET_SIZE: u16 = 6;
+
+const QUEUE_LEN: usize = 4;
+
+// bInfo: class-specific message originating from an interface entity
+const INFO_INTERFACE: u8 = 0x00;
+
+/// Pending interrupt data messages, sent to the host in order
+pub struct StatusQueue {
+    messages: [[u8; 6]; QUEUE_LEN],
+    len: usize,
+}
+
+impl StatusQueue {
+
+    pub fn new() -> StatusQueue {
+        StatusQueue {
+            messages: [[0; 6]; QUEUE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Queue a control change message for the given entity. Returns false
+    /// and drops the message if the queue is full.
+    pub fn push(&mut self, interface: u8, entity: u8, selector: u8, channel: u8) -> bool {
+        if self.len == QUEUE_LEN {
+            return false;
+        }
+
+        self.messages[self.len] = [
+            INFO_INTERFACE, // bInfo
+            CUR, // bAttribute
+            channel, // wValue: channel number
+            selector, // wValue: control selector
+            interface, // wIndex: interface
+            entity, // wIndex: entity ID
+        ];
+        self.len += 1;
+
+        true
+    }
+
+    /// Send the oldest queued message if the endpoint is free
+    pub fn flush<B: UsbBus>(&mut self, endpoint: &Endpoint<'_, B, In>) {
+        if self.len == 0 {
+            return;
+        }
+
+        // a busy endpoint keeps the message queued for the next poll
+        if endpoint.write(&self.messages[0]).is_ok() {
+            self.messages.copy_within(1..self.len, 0);
+            self.len -= 1;
+        }
+    }
+
+}
@@ -4,59 +4,146 @@
 
 /// USB Audio Terminal Types from "Universal Serial Bus Device Class Definition
 /// for Terminal Types, Release 1.0"
-#[rustfmt::skip]
-#[repr(u16)]
 #[non_exhaustive]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum TerminalType {
 
     // USB Terminal Types
-    UsbUndefined                    = 0x0100,
-    UsbStreaming                    = 0x0101,
-    UsbVendor                       = 0x01ff,
+    UsbUndefined,
+    UsbStreaming,
+    UsbVendor,
 
     // Input Terminal Types
-    InUndefined                     = 0x0200,
-    InMicrophone                    = 0x0201,
-    InDesktopMicrophone             = 0x0202,
-    InPersonalMicrophone            = 0x0203,
-    InOmniDirectionalMicrophone     = 0x0204,
-    InMicrophoneArray               = 0x0205,
-    InProcessingMicrophoneArray     = 0x0206,
+    InUndefined,
+    InMicrophone,
+    InDesktopMicrophone,
+    InPersonalMicrophone,
+    InOmniDirectionalMicrophone,
+    InMicrophoneArray,
+    InProcessingMicrophoneArray,
 
     // Output Terminal Types
-    OutUndefined                    = 0x0300,
-    OutSpeaker                      = 0x0301,
-    OutHeadphones                   = 0x0302,
-    OutHeadMountedDisplayAudio      = 0x0303,
-    OutDesktopSpeaker               = 0x0304,
-    OutRoomSpeaker                  = 0x0305,
-    OutCommunicationSpeaker         = 0x0306,
-    OutLowFrequencyEffectsSpeaker   = 0x0307,
+    OutUndefined,
+    OutSpeaker,
+    OutHeadphones,
+    OutHeadMountedDisplayAudio,
+    OutDesktopSpeaker,
+    OutRoomSpeaker,
+    OutCommunicationSpeaker,
+    OutLowFrequencyEffectsSpeaker,
 
     // External Terminal Types
-    ExtUndefined                    = 0x0600,
-    ExtAnalogConnector              = 0x0601,
-    ExtDigitalAudioInterface        = 0x0602,
-    ExtLineConnector                = 0x0603,
-    ExtLegacyAudioConnector         = 0x0604,
-    ExtSpdifConnector               = 0x0605,
-    Ext1394DaStream                 = 0x0606,
-    Ext1394DvStreamSoundtrack       = 0x0607,
+    ExtUndefined,
+    ExtAnalogConnector,
+    ExtDigitalAudioInterface,
+    ExtLineConnector,
+    ExtLegacyAudioConnector,
+    ExtSpdifConnector,
+    Ext1394DaStream,
+    Ext1394DvStreamSoundtrack,
+
+    // Embedded Function Terminal Types
+    EmbUndefined,
+    EmbLevelCalibrationNoiseSource,
+    EmbEqualizationNoise,
+    EmbCdPlayer,
+    EmbDat,
+    EmbDcc,
+    EmbMiniDisk,
+    EmbAnalogTape,
+    EmbPhonograph,
+    EmbVcrAudio,
+    EmbVideoDiscAudio,
+    EmbDvdAudio,
+    EmbTvTunerAudio,
+    EmbSatelliteReceiverAudio,
+    EmbCableTunerAudio,
+    EmbDssAudio,
+    EmbRadioReceiver,
+    EmbRadioTransmitter,
+    EmbMultiTrackRecorder,
+    EmbSynthesizer,
+
+    /// A terminal type code not listed above, such as a telephony type or
+    /// one of the vendor's own. It is described as given, but does not
+    /// compare equal to the variant of a listed code.
+    Raw(u16),
 }
 
 impl From<TerminalType> for u16 {
     fn from(t: TerminalType) -> u16 {
-        t as u16
+        t.code()
     }
 }
 
 impl TerminalType {
+
+    /// The wTerminalType code
+    #[rustfmt::skip]
+    pub fn code(&self) -> u16 {
+        match self {
+            // USB Terminal Types
+            TerminalType::UsbUndefined                    => 0x0100,
+            TerminalType::UsbStreaming                    => 0x0101,
+            TerminalType::UsbVendor                       => 0x01ff,
+
+            // Input Terminal Types
+            TerminalType::InUndefined                     => 0x0200,
+            TerminalType::InMicrophone                    => 0x0201,
+            TerminalType::InDesktopMicrophone             => 0x0202,
+            TerminalType::InPersonalMicrophone            => 0x0203,
+            TerminalType::InOmniDirectionalMicrophone     => 0x0204,
+            TerminalType::InMicrophoneArray               => 0x0205,
+            TerminalType::InProcessingMicrophoneArray     => 0x0206,
+
+            // Output Terminal Types
+            TerminalType::OutUndefined                    => 0x0300,
+            TerminalType::OutSpeaker                      => 0x0301,
+            TerminalType::OutHeadphones                   => 0x0302,
+            TerminalType::OutHeadMountedDisplayAudio      => 0x0303,
+            TerminalType::OutDesktopSpeaker               => 0x0304,
+            TerminalType::OutRoomSpeaker                  => 0x0305,
+            TerminalType::OutCommunicationSpeaker         => 0x0306,
+            TerminalType::OutLowFrequencyEffectsSpeaker   => 0x0307,
+
+            // External Terminal Types
+            TerminalType::ExtUndefined                    => 0x0600,
+            TerminalType::ExtAnalogConnector              => 0x0601,
+            TerminalType::ExtDigitalAudioInterface        => 0x0602,
+            TerminalType::ExtLineConnector                => 0x0603,
+            TerminalType::ExtLegacyAudioConnector         => 0x0604,
+            TerminalType::ExtSpdifConnector               => 0x0605,
+            TerminalType::Ext1394DaStream                 => 0x0606,
+            TerminalType::Ext1394DvStreamSoundtrack       => 0x0607,
+
+            // Embedded Function Terminal Types
+            TerminalType::EmbUndefined                    => 0x0700,
+            TerminalType::EmbLevelCalibrationNoiseSource  => 0x0701,
+            TerminalType::EmbEqualizationNoise            => 0x0702,
+            TerminalType::EmbCdPlayer                     => 0x0703,
+            TerminalType::EmbDat                          => 0x0704,
+            TerminalType::EmbDcc                          => 0x0705,
+            TerminalType::EmbMiniDisk                     => 0x0706,
+            TerminalType::EmbAnalogTape                   => 0x0707,
+            TerminalType::EmbPhonograph                   => 0x0708,
+            TerminalType::EmbVcrAudio                     => 0x0709,
+            TerminalType::EmbVideoDiscAudio               => 0x070a,
+            TerminalType::EmbDvdAudio                     => 0x070b,
+            TerminalType::EmbTvTunerAudio                 => 0x070c,
+            TerminalType::EmbSatelliteReceiverAudio       => 0x070d,
+            TerminalType::EmbCableTunerAudio              => 0x070e,
+            TerminalType::EmbDssAudio                     => 0x070f,
+            TerminalType::EmbRadioReceiver                => 0x0710,
+            TerminalType::EmbRadioTransmitter             => 0x0711,
+            TerminalType::EmbMultiTrackRecorder           => 0x0712,
+            TerminalType::EmbSynthesizer                  => 0x0713,
+
+            TerminalType::Raw(code)                       => *code,
+        }
+    }
+
     pub fn as_bytes(&self) -> [u8; 2] {
-        let d = [
-            *self as u8,
-            (*self as u16 >> 8) as u8,
-        ];
-        d
+        self.code().to_le_bytes()
     }
-}
\ No newline at end of file
+
+}
@@ -0,0 +1,306 @@
+//! Audio Class 1.0 descriptors and requests as defined in Universal Serial
+//! Bus Device Class Definition for Audio Devices, Release 1.0 and Universal
+//! Serial Bus Device Class Definition for Audio Data Formats, Release 1.0, for
+//! hosts without Audio Class 2.0 support. Only terminals are described: each
+//! stream runs straight from its input terminal to its output terminal, with
+//! its sampling frequency set by the host through the endpoint control and
+//! held in the stream's clock, which is not described.
+//!
+
+use usb_device::{
+    class_prelude::*,
+    control::{Recipient, RequestType},
+    descriptor::descriptor_type::{ENDPOINT, INTERFACE},
+    endpoint::EndpointDirection,
+};
+
+use crate::{
+    class_codes::*,
+    AudioStream, AudioStreams, Clock, Cluster, ControlKind, ControlState, SampleRate, TerminalType,
+    accept_truncated,
+    ID_INPUT_SINK, ID_INPUT_STREAMING, ID_INPUT_TERMINAL, ID_OUTPUT_STREAMING, ID_OUTPUT_TERMINAL,
+};
+
+// wChannelConfig only has the first 12 spatial locations, which keep their
+// bmChannelConfig bits
+const CHANNEL_CONFIG_MASK: u32 = 0x0FFF;
+
+/// The AudioControl header and terminals, in place of the 2.0 entities
+pub fn ac_descriptors<B: UsbBus>(control: &ControlState<B>, streams: &AudioStreams<B>, writer: &mut DescriptorWriter, ac_length: u16) -> usb_device::Result<()> {
+
+    let total_length: [u8; 2] = ac_length.to_le_bytes();
+
+    // the header lists the streaming interfaces of the collection
+    let mut header: [u8; 8] = [
+        HEADER,
+        0x00, // bcdADC 1.00
+        0x01,
+        total_length[0], // wTotalLength
+        total_length[1],
+        0x00, // bInCollection
+        0x00, // baInterfaceNr
+        0x00,
+    ];
+    let mut n_interfaces = 0;
+
+    for interface in [streams.input.as_ref().map(|si| si.interface), streams.output.as_ref().map(|si| si.interface)].into_iter().flatten() {
+        header[6 + n_interfaces] = interface.into();
+        n_interfaces += 1;
+    }
+
+    header[5] = n_interfaces as u8;
+    let length = 6 + n_interfaces;
+
+    writer.write(CS_INTERFACE, &header[..length])?;
+
+    if let Some(ref input) = streams.input {
+        let assoc = if control.associate_terminals && streams.output.is_some() { ID_OUTPUT_TERMINAL } else { 0x00 };
+
+        // the physical terminal may carry more channels than the USB stream
+        let cluster: Cluster = input.stream_config.terminal_cluster.unwrap_or(Cluster {
+            n_channels: input.stream_config.n_channels,
+            channel_config: input.stream_config.channel_config.bits(),
+        });
+
+        input_terminal(writer, ID_INPUT_TERMINAL, input.stream_config.term_type, assoc, cluster, input.string_index(input.channel_names), input.string_index(input.terminal_name))?;
+        output_terminal(writer, ID_INPUT_STREAMING, TerminalType::UsbStreaming, 0x00, ID_INPUT_TERMINAL, 0x00)?;
+
+        // additional non-USB sink fed from the same input terminal
+        if let Some(sink) = control.input_sink {
+            output_terminal(writer, ID_INPUT_SINK, sink, 0x00, ID_INPUT_TERMINAL, 0x00)?;
+        }
+    }
+
+    if let Some(ref output) = streams.output {
+        let assoc = if control.associate_terminals && streams.input.is_some() { ID_INPUT_TERMINAL } else { 0x00 };
+
+        let cluster = Cluster {
+            n_channels: output.stream_config.n_channels,
+            channel_config: output.stream_config.channel_config.bits(),
+        };
+
+        input_terminal(writer, ID_OUTPUT_STREAMING, TerminalType::UsbStreaming, 0x00, cluster, output.string_index(output.channel_names), 0x00)?;
+        output_terminal(writer, ID_OUTPUT_TERMINAL, output.stream_config.term_type, assoc, ID_OUTPUT_STREAMING, output.string_index(output.terminal_name))?;
+    }
+
+    Ok(())
+
+}
+
+fn input_terminal(writer: &mut DescriptorWriter, id: u8, term_type: TerminalType, assoc: u8, cluster: Cluster, channel_names: u8, name: u8) -> usb_device::Result<()> {
+
+    let term_type: [u8; 2] = term_type.as_bytes();
+    let channel_config: [u8; 2] = ((cluster.channel_config & CHANNEL_CONFIG_MASK) as u16).to_le_bytes();
+
+    writer.write(CS_INTERFACE, &[
+        INPUT_TERMINAL,
+        id, // terminal ID
+        term_type[0], // terminal type
+        term_type[1],
+        assoc, // associated terminal
+        cluster.n_channels, // logical channels
+        channel_config[0], // spacial description config
+        channel_config[1],
+        channel_names, // channel names string index
+        name, // terminal desc string index
+    ])
+
+}
+
+fn output_terminal(writer: &mut DescriptorWriter, id: u8, term_type: TerminalType, assoc: u8, source: u8, name: u8) -> usb_device::Result<()> {
+
+    let term_type: [u8; 2] = term_type.as_bytes();
+
+    writer.write(CS_INTERFACE, &[
+        OUTPUT_TERMINAL,
+        id, // terminal ID
+        term_type[0], // terminal type
+        term_type[1],
+        assoc, // associated terminal
+        source, // source ID
+        name, // terminal desc string index
+    ])
+
+}
+
+/// Streaming interfaces with their 1.0 class and endpoint descriptors
+pub fn streaming_descriptors<B: UsbBus>(control: &ControlState<B>, streams: &AudioStreams<B>, writer: &mut DescriptorWriter) -> usb_device::Result<()> {
+
+    if let Some(ref input) = streams.input {
+        as_ep_descriptor(input, control.stream_clock(input.clock_id), control.feedback_refresh, writer, ID_INPUT_STREAMING)?;
+    }
+
+    if let Some(ref output) = streams.output {
+        as_ep_descriptor(output, control.stream_clock(output.clock_id), control.feedback_refresh, writer, ID_OUTPUT_STREAMING)?;
+    }
+
+    Ok(())
+
+}
+
+fn as_ep_descriptor<B: UsbBus, D: EndpointDirection>(stream: &AudioStream<B, D>, clock: Option<&Clock>, refresh: u8, writer: &mut DescriptorWriter, terminal_link: u8) -> usb_device::Result<()> {
+
+    let rates: &[u32] = clock.map_or(&[], Clock::supported_rates);
+
+    // sampling frequency control, set per endpoint
+    let frequency_control: u8 = if clock.is_some_and(|clock| clock.source.frequency_control) { 0b01 } else { 0b00 };
+
+    // alt 0 has no endpoints so the host can idle the stream
+    writer.interface(stream.interface, AUDIO, AUDIOSTREAMING, IP_UNDEFINED)?;
+
+    // one alt setting per offered subslot, as its first format since a 1.0
+    // alt setting carries a single wFormatTag
+    for alt in 1..=stream.stream_config.alt_settings() {
+        let (format, _, slot) = stream.stream_config.alt_format(alt).unwrap();
+        let format_tag: [u8; 2] = format.format_tag().to_le_bytes();
+
+        writer.write(INTERFACE, &[
+            stream.interface.into(),
+            alt, // alternate setting
+            0x01 + stream.feedback.is_some() as u8, // n endpoints (data and feedback)
+            AUDIO,
+            AUDIOSTREAMING,
+            IP_UNDEFINED,
+            0x00,
+        ])?;
+
+        writer.write(CS_INTERFACE, &[
+            AS_GENERAL,
+            terminal_link, // bTerminalLink
+            0x01, // bDelay (frames)
+            format_tag[0], // wFormatTag
+            format_tag[1],
+        ])?;
+
+        writer.write_with(CS_INTERFACE, |buf| {
+            let len = 6 + 3 * rates.len();
+
+            if buf.len() < len {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            buf[..6].copy_from_slice(&[
+                FORMAT_TYPE,
+                FORMAT_TYPE_I,
+                stream.stream_config.n_channels, // bNrChannels
+                slot.0, // bSubframeSize
+                slot.1, // bBitResolution
+                rates.len() as u8, // bSamFreqType (discrete rates)
+            ]);
+
+            // tSamFreq, 3 bytes per rate
+            for (freq, rate) in buf[6..len].chunks_exact_mut(3).zip(rates) {
+                freq.copy_from_slice(&rate.to_le_bytes()[..3]);
+            }
+
+            Ok(len)
+        })?;
+
+        // ENDPOINT DESCRIPTORS
+        // audio endpoints are 9 bytes long in 1.0, with bRefresh and
+        // bSynchAddress after the standard fields
+        let max_transfer: [u8; 2] = stream.endpoint.max_packet_size().to_le_bytes();
+
+        writer.write(ENDPOINT, &[
+            stream.endpoint.address().into(),
+            stream.attributes.bm_attributes(), // bmAttributes
+            max_transfer[0],
+            max_transfer[1],
+            stream.endpoint.interval(),
+            0x00, // bRefresh
+            stream.feedback.as_ref().map_or(0x00, |feedback| feedback.address().into()), // bSynchAddress
+        ])?;
+
+        writer.write(CS_ENDPOINT, &[
+            EP_GENERAL,
+            stream.attributes.cs_bm_attributes() | frequency_control, // bmAttributes
+            0x00, // bLockDelayUnits
+            0x00, 0x00 // wLockDelay
+        ])?;
+
+        if let Some(ref feedback) = stream.feedback {
+            writer.write(ENDPOINT, &[
+                feedback.address().into(),
+                feedback.ep_type().to_bm_attributes(), // bmAttributes
+                feedback.max_packet_size() as u8,
+                0x00,
+                feedback.interval(),
+                refresh, // bRefresh, feedback every 2^refresh ms
+                0x00, // bSynchAddress
+            ])?;
+        }
+    }
+
+    Ok(())
+
+}
+
+// the clock of the stream whose data endpoint a request addresses
+fn endpoint_clock_id<B: UsbBus>(streams: &AudioStreams<B>, address: u8) -> Option<u8> {
+    let input = streams.input.as_ref().filter(|si| u8::from(si.endpoint.address()) == address).map(|si| si.clock_id);
+    let output = streams.output.as_ref().filter(|so| u8::from(so.endpoint.address()) == address).map(|so| so.clock_id);
+
+    input.or(output)
+}
+
+/// Answer GET_CUR of an endpoint's sampling frequency control
+pub fn control_in<B: UsbBus>(control: &ControlState<B>, streams: &AudioStreams<B>, xfer: ControlIn<B>) -> Option<ControlKind> {
+
+    let req = xfer.request();
+
+    if req.request_type != RequestType::Class
+        || req.recipient != Recipient::Endpoint
+        || req.value >> 8 != SAMPLING_FREQ_CONTROL
+        || req.request != GET_CUR
+    {
+        return None;
+    }
+
+    let clock = control.stream_clock(endpoint_clock_id(streams, req.index as u8)?)?;
+
+    if !clock.source.frequency_control {
+        return None;
+    }
+
+    accept_truncated(xfer, &clock.rate.to_le_bytes()[..3]).ok();
+    Some(ControlKind::ClockCur)
+
+}
+
+/// Handle SET_CUR of an endpoint's sampling frequency control
+pub fn control_out<B: UsbBus>(control: &mut ControlState<B>, streams: &AudioStreams<B>, xfer: ControlOut<B>) -> Option<ControlKind> {
+
+    let req = xfer.request();
+
+    if req.request_type != RequestType::Class
+        || req.recipient != Recipient::Endpoint
+        || req.value >> 8 != SAMPLING_FREQ_CONTROL
+        || req.request != SET_CUR
+    {
+        return None;
+    }
+
+    let id = endpoint_clock_id(streams, req.index as u8)?;
+    let clock = control.clocks.iter_mut().flatten().find(|clock| clock.id == id)?;
+
+    if !clock.source.frequency_control {
+        return None;
+    }
+
+    let requested = match xfer.data() {
+        [b0, b1, b2, ..] => SampleRate::from_bytes([*b0, *b1, *b2, 0x00]),
+        _ => {
+            xfer.reject().ok();
+            return None;
+        }
+    };
+
+    if clock.set_rate(requested) {
+        xfer.accept().ok();
+        Some(ControlKind::ClockCur)
+    } else {
+        xfer.reject().ok();
+        None
+    }
+
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use usbd_audio_2::{AudioClassBuilder, Error, Format, StreamConfig, TerminalType, UsbSpeed};
+
+use common::*;
+
+#[test]
+fn over_budget_duplex_is_reported() {
+    // 49 frames of 4 channels of 3 byte samples each way, 588 bytes, plus the
+    // 3 byte feedback packet
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S24LE, 4, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S24LE, 4, TerminalType::OutSpeaker, 48000).unwrap());
+
+    assert!(matches!(
+        builder.validate_bandwidth(UsbSpeed::Full),
+        Err(Error::BandwidthExceeded { required: 1179, available: 1023 })
+    ));
+
+    // at high speed both are sized per 125 us microframe, 7 frames each way
+    // plus the 4 byte feedback packet
+    assert!(builder.validate_bandwidth(UsbSpeed::High).is_ok());
+}
+
+#[test]
+fn stereo_duplex_fits_a_frame() {
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S24LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap());
+
+    assert!(builder.validate_bandwidth(UsbSpeed::Full).is_ok());
+    assert!(builder.build(allocator()).is_ok());
+}
+
+fn hi_res() -> StreamConfig<'static> {
+    StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 192000).unwrap()
+}
+
+#[test]
+fn high_rate_stream_builds_at_high_speed() {
+    let class = AudioClassBuilder::new()
+        .output(hi_res())
+        .high_speed(true)
+        .build(allocator());
+
+    assert!(class.is_ok());
+}
+
+#[test]
+fn high_rate_stream_is_rejected_at_full_speed() {
+    let class = AudioClassBuilder::new()
+        .output(hi_res())
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::BandwidthExceeded { available: 1023, .. })));
+}
+
+#[test]
+fn stream_too_large_for_any_speed_is_rejected() {
+    let config = StreamConfig::new(Format::S32LE, 32, TerminalType::OutSpeaker, 192000);
+
+    assert!(matches!(config.err(), Some(Error::BandwidthExceeded { available: 1024, .. })));
+}
+
+#[test]
+fn larger_format_is_checked() {
+    static FORMATS: [Format; 1] = [Format::S32LE];
+
+    let config = StreamConfig::new(Format::S16LE, 16, TerminalType::OutSpeaker, 192000)
+        .unwrap()
+        .with_formats(&FORMATS);
+
+    assert!(matches!(config.err(), Some(Error::BandwidthExceeded { .. })));
+}
+
+#[test]
+fn alignment_padding_is_checked() {
+    // 49 samples of 10 16 bit channels, 980 bytes
+    let config = StreamConfig::new(Format::S16LE, 10, TerminalType::OutSpeaker, 48000).unwrap();
+    assert_eq!(config.packet_size(), 980);
+
+    let padded = config.with_alignment(1024).unwrap();
+    assert_eq!(padded.packet_size(), 1024);
+
+    let class = AudioClassBuilder::new()
+        .output(padded)
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::BandwidthExceeded { available: 1023, .. })));
+}
+
+#[test]
+fn alignment_beyond_any_endpoint_is_rejected() {
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000)
+        .unwrap()
+        .with_alignment(2048);
+
+    assert!(matches!(config.err(), Some(Error::BandwidthExceeded { required: 2048, .. })));
+}
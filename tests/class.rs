@@ -0,0 +1,227 @@
+mod common;
+
+use usb_device::{class::UsbClass, endpoint::EndpointAddress, UsbDirection, UsbError};
+use usbd_audio_2::{AudioClassBuilder, ControlKind, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+fn microphone() -> StreamConfig<'static> {
+    StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap()
+}
+
+fn speaker() -> StreamConfig<'static> {
+    StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap()
+}
+
+#[test]
+fn stream_count_input_only() {
+    let class = AudioClassBuilder::new().input(microphone()).build(allocator()).unwrap();
+
+    assert_eq!(class.stream_count(), 1);
+}
+
+#[test]
+fn stream_count_output_only() {
+    let class = AudioClassBuilder::new().output(speaker()).build(allocator()).unwrap();
+
+    assert_eq!(class.stream_count(), 1);
+}
+
+#[test]
+fn stream_count_duplex() {
+    let class = AudioClassBuilder::new().input(microphone()).output(speaker()).build(allocator()).unwrap();
+
+    assert_eq!(class.stream_count(), 2);
+}
+
+#[test]
+fn alt_setting_controls_report_the_streaming_interface() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(microphone())
+        .alt_setting_controls(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // AS_GENERAL bmControls: both controls read only
+    let general = descriptors(&config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x01 && d.len() == 16).unwrap();
+    assert_eq!(general[4], 0b0101);
+
+    // valid alternate settings of interface 1: bControlSize, alt 0 and 1
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x02, 0x01, 0x00, 0x02, 0x00]).unwrap(), [0x01, 0b11]);
+
+    // active alternate setting
+    let active = [0xA1, 0x01, 0x00, 0x01, 0x01, 0x00, 0x01, 0x00];
+    assert_eq!(control_in(&mut device, &mut class, active).unwrap(), [0x00]);
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    assert_eq!(control_in(&mut device, &mut class, active).unwrap(), [0x01]);
+}
+
+#[test]
+fn alt_setting_controls_are_off_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new().input(microphone()).build(allocator).unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x02, 0x01, 0x00, 0x02, 0x00]), None);
+}
+
+#[test]
+fn streams_are_idle_at_alt_0() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new().input(microphone()).output(speaker()).build(allocator).unwrap();
+    let mut device = device(allocator);
+    let mut buf = [0u8; 192];
+
+    receive(&[0x00; 4]);
+    assert!(matches!(class.read(&mut buf), Err(Error::StreamIdle)));
+    assert!(matches!(class.write(&[0x00; 4]), Err(Error::StreamIdle)));
+
+    // the host starts both streams
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    assert!(set_interface(&mut device, &mut class, 2, 1));
+    assert_eq!(class.read(&mut buf).unwrap(), 4);
+    assert_eq!(class.write(&[0x00; 4]).unwrap(), 4);
+
+    // and stops them again
+    assert!(set_interface(&mut device, &mut class, 1, 0));
+    assert!(set_interface(&mut device, &mut class, 2, 0));
+    assert!(matches!(class.read(&mut buf), Err(Error::StreamIdle)));
+    assert!(matches!(class.write(&[0x00; 4]), Err(Error::StreamIdle)));
+}
+
+// GET_INTERFACE of `interface`
+fn get_interface(interface: u8) -> [u8; 8] {
+    [0x81, 0x0A, 0x00, 0x00, interface, 0x00, 0x01, 0x00]
+}
+
+#[test]
+fn get_interface_answers_for_the_class_interfaces_only() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new().input(microphone()).build(allocator).unwrap();
+    let mut device = device(allocator);
+
+    // the control interface has only alt 0
+    assert_eq!(control_in(&mut device, &mut class, get_interface(0)).unwrap(), [0]);
+    assert_eq!(class.take_last_control(), Some(ControlKind::GetInterface));
+
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    class.take_last_control();
+    assert_eq!(control_in(&mut device, &mut class, get_interface(1)).unwrap(), [1]);
+    assert_eq!(class.take_last_control(), Some(ControlKind::GetInterface));
+
+    // another class's interface is left to it
+    control_in(&mut device, &mut class, get_interface(4));
+    assert_eq!(class.take_last_control(), None);
+}
+
+#[test]
+fn get_interface_is_truncated_to_w_length() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new().input(microphone()).build(allocator).unwrap();
+    let mut device = device(allocator);
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+
+    // GET_INTERFACE of interface 1 asking for more than its one byte, and
+    // for none of it
+    assert_eq!(control_in(&mut device, &mut class, [0x81, 0x0A, 0x00, 0x00, 0x01, 0x00, 0x04, 0x00]).unwrap(), [1]);
+    assert_eq!(control_in(&mut device, &mut class, [0x81, 0x0A, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]).unwrap(), []);
+}
+
+#[test]
+fn discard_output_drains_received_packets() {
+    for discard in [true, false] {
+        let allocator = common::allocator();
+        let mut class = AudioClassBuilder::new().output(speaker()).discard_output(discard).build(allocator).unwrap();
+        let mut device = device(allocator);
+        let config = configuration(&mut device, &mut class);
+        assert!(set_interface(&mut device, &mut class, 1, 1));
+
+        // the isochronous data endpoint of the output stream
+        let endpoint = descriptors(&config)
+            .into_iter()
+            .find(|d| d[1] == 0x05 && d[2] & 0x80 == 0)
+            .map(|d| EndpointAddress::from_parts((d[2] & 0x0F) as usize, UsbDirection::Out))
+            .unwrap();
+
+        receive(&[0x01, 0x02, 0x03, 0x04]);
+        class.endpoint_out(endpoint);
+
+        // drained by the class, or left for firmware to read
+        let mut buf = [0u8; 196];
+        if discard {
+            assert!(matches!(class.read(&mut buf), Err(Error::UsbError(UsbError::WouldBlock))));
+        } else {
+            assert_eq!(class.read(&mut buf).unwrap(), 4);
+        }
+    }
+}
+
+#[test]
+fn read_ready_follows_the_output_endpoint() {
+    for discard in [false, true] {
+        let allocator = common::allocator();
+        let mut class = AudioClassBuilder::new().output(speaker()).discard_output(discard).build(allocator).unwrap();
+        let mut device = device(allocator);
+        let config = configuration(&mut device, &mut class);
+        assert!(set_interface(&mut device, &mut class, 1, 1));
+
+        let endpoint = descriptors(&config)
+            .into_iter()
+            .find(|d| d[1] == 0x05 && d[2] & 0x80 == 0)
+            .map(|d| EndpointAddress::from_parts((d[2] & 0x0F) as usize, UsbDirection::Out))
+            .unwrap();
+
+        assert!(!class.read_ready());
+
+        receive(&[0x01, 0x02, 0x03, 0x04]);
+        class.endpoint_out(endpoint);
+
+        // a discarded packet is never ready, a kept one until it is read
+        assert_eq!(class.read_ready(), !discard);
+
+        let mut buf = [0u8; 196];
+        class.read(&mut buf).ok();
+        assert!(!class.read_ready());
+    }
+}
+
+#[test]
+fn set_interface_to_an_undescribed_alt_setting_stalls() {
+    static FORMATS: [Format; 1] = [Format::S24LE];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(speaker().with_formats(&FORMATS).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    configuration(&mut device, &mut class);
+
+    // alt 1 and 2 carry the two formats
+    assert!(set_interface(&mut device, &mut class, 1, 2));
+    assert_eq!(class.output_alt_setting().unwrap(), 2);
+
+    // there is no alt 3, and the current setting is kept
+    assert!(!set_interface(&mut device, &mut class, 1, 3));
+    assert_eq!(class.output_alt_setting().unwrap(), 2);
+}
+
+#[test]
+fn running_out_of_endpoints_is_an_error() {
+    let allocator = allocator();
+
+    // another class on the device has taken all but one endpoint
+    for _ in 0..14 {
+        let _ = allocator.interrupt::<usb_device::endpoint::In>(8, 1);
+    }
+
+    let class = AudioClassBuilder::new()
+        .input(microphone())
+        .output(speaker())
+        .build(allocator);
+
+    assert!(matches!(class.err(), Some(Error::UsbError(UsbError::EndpointOverflow))));
+}
@@ -0,0 +1,509 @@
+mod common;
+
+use usb_device::class::UsbClass;
+use usb_device::UsbDirection;
+use usbd_audio_2::{AudioClass, AudioClassBuilder, ClockSource, ClockType, Error, Format, RatePolicy, SampleRate, StreamConfig, TerminalType};
+
+use common::*;
+
+// GET_CUR of the sampling frequency control of `clock` on interface 0
+fn frequency(clock: u8) -> [u8; 8] {
+    [0xA1, 0x01, 0x00, 0x01, 0x00, clock, 0x04, 0x00]
+}
+
+// The endpoint number of the status interrupt endpoint
+fn status_endpoint(config: &[u8]) -> usize {
+    let endpoint = descriptors(config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x03 == 0x03).unwrap();
+
+    (endpoint[2] & 0x0F) as usize
+}
+
+// Give the class time to send every queued status message, one per poll
+fn flush(class: &mut AudioClass<'static, MockBus>) {
+    for _ in 0..8 {
+        class.poll();
+    }
+}
+
+#[test]
+fn duplex_clocks_report_their_own_rates() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 44100).unwrap())
+        .independent_clocks(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // Clock Source descriptors: bClockID
+    let clocks: Vec<u8> = control_descriptors(&config).into_iter().filter(|d| d[2] == 0x0A).map(|d| d[3]).collect();
+    assert_eq!(clocks, [0x01, 0x06]);
+
+    // every terminal takes its clock from its own path: bTerminalID, bCSourceID
+    for d in control_descriptors(&config) {
+        let clock = match d[2] {
+            0x02 => d[7],
+            0x03 => d[8],
+            _ => continue,
+        };
+
+        match d[3] {
+            0x02 | 0x03 => assert_eq!(clock, 0x01),
+            0x04 | 0x05 => assert_eq!(clock, 0x06),
+            id => panic!("unexpected terminal {id}"),
+        }
+    }
+
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x01)).unwrap(), 48000u32.to_le_bytes());
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x06)).unwrap(), 44100u32.to_le_bytes());
+}
+
+#[test]
+fn duplex_shares_one_clock_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    assert_eq!(control_descriptors(&config).into_iter().filter(|d| d[2] == 0x0A).count(), 1);
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x01)).unwrap(), 48000u32.to_le_bytes());
+
+    // no such clock
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x06)), None);
+}
+
+#[test]
+fn validity_change_notifies_once() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .independent_clocks(true)
+        .status_interrupt(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let status = status_endpoint(&configuration(&mut device, &mut class));
+
+    // bInfo, bAttribute CUR, channel, CS_CLOCK_VALID_CONTROL, interface, clock
+    let message = |clock: u8| vec![0x00, 0x01, 0x00, 0x02, 0x00, clock];
+
+    class.set_clock_valid(0x01, true).unwrap();
+    flush(&mut class);
+    assert_eq!(written(status), [message(0x01)]);
+
+    // unchanged: nothing to report
+    class.set_clock_valid(0x01, true).unwrap();
+    flush(&mut class);
+    assert!(written(status).is_empty());
+
+    class.set_clock_valid(0x01, false).unwrap();
+    class.set_clock_valid(0x01, false).unwrap();
+    flush(&mut class);
+    assert_eq!(written(status), [message(0x01)]);
+
+    // the output's clock changes on its own
+    class.set_clock_valid(0x06, true).unwrap();
+    flush(&mut class);
+    assert_eq!(written(status), [message(0x06)]);
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [0]);
+
+    assert!(matches!(class.set_clock_valid(0x02, true), Err(Error::UnknownClock)));
+}
+
+#[test]
+fn full_queue_reports_the_dropped_notification() {
+    static SOURCES: [ClockSource<'static>; 3] = [ClockSource::new(), ClockSource::new(), ClockSource::new()];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .independent_clocks(true)
+        .clock_selector(&SOURCES)
+        .status_interrupt(true)
+        .build(allocator)
+        .unwrap();
+
+    // five clocks, four queue slots, no poll in between
+    for clock in [0x01, 0x06, 0x0C, 0x0D] {
+        class.set_clock_valid(clock, true).unwrap();
+    }
+    assert!(matches!(class.set_clock_valid(0x0E, true), Err(Error::StatusQueueFull)));
+}
+
+// GET_CUR of the clock validity control of clock source 0x01 on interface 0
+const VALIDITY: [u8; 8] = [0xA1, 0x01, 0x00, 0x02, 0x00, 0x01, 0x01, 0x00];
+
+#[test]
+fn clock_starts_invalid() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [0]);
+
+    class.set_clock_valid(0x01, true).unwrap();
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [1]);
+}
+
+#[test]
+fn minimal_input_clock_is_valid() {
+    let allocator = allocator();
+    let mut class = AudioClass::minimal_input(allocator).unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [1]);
+}
+
+// bmControls of the Clock Source descriptors
+fn clock_controls(config: &[u8]) -> Vec<u8> {
+    control_descriptors(config).into_iter().filter(|d| d[2] == 0x0A).map(|d| d[5]).collect()
+}
+
+#[test]
+fn frequency_control_can_be_left_out() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .frequency_control(false)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // validity read only, no frequency control bits
+    assert_eq!(clock_controls(&configuration(&mut device, &mut class)), [0b0000_0100]);
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x01)), None);
+}
+
+#[test]
+fn frequency_control_is_read_only_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(clock_controls(&configuration(&mut device, &mut class)), [0b0000_0101]);
+}
+
+// SET_CUR of the sampling frequency control of the clock
+const SET_FREQUENCY: [u8; 8] = [0x21, 0x01, 0x00, 0x01, 0x00, 0x01, 0x04, 0x00];
+
+#[test]
+fn strict_policy_stalls_an_unsupported_rate() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert!(!control_out(&mut device, &mut class, SET_FREQUENCY, &44100u32.to_le_bytes()));
+    assert!(control_out(&mut device, &mut class, SET_FREQUENCY, &48000u32.to_le_bytes()));
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x01)).unwrap(), 48000u32.to_le_bytes());
+}
+
+#[test]
+fn clamp_policy_reports_the_nearest_rate() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .rate_policy(RatePolicy::Clamp)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert!(control_out(&mut device, &mut class, SET_FREQUENCY, &44100u32.to_le_bytes()));
+    assert_eq!(control_in(&mut device, &mut class, frequency(0x01)).unwrap(), 48000u32.to_le_bytes());
+}
+
+#[test]
+fn current_sample_rate_is_the_clock_rate() {
+    let class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 44100).unwrap())
+        .build(allocator())
+        .unwrap();
+
+    assert_eq!(class.current_sample_rate(), Some(SampleRate::Hz44100));
+}
+
+// bmAttributes of the Clock Source descriptors
+fn clock_attributes(config: &[u8]) -> Vec<u8> {
+    control_descriptors(config).into_iter().filter(|d| d[2] == 0x0A).map(|d| d[4]).collect()
+}
+
+#[test]
+fn clock_can_be_synchronized_to_sof() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .clock_sof_sync(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // internal fixed clock, synchronized to SOF
+    assert_eq!(clock_attributes(&configuration(&mut device, &mut class)), [0b0000_0101]);
+}
+
+#[test]
+fn clock_runs_free_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(clock_attributes(&configuration(&mut device, &mut class)), [0b0000_0001]);
+}
+
+// GET_CUR of a control of the clock multiplier, entity 0x0A on interface 0
+fn multiplier(selector: u8) -> [u8; 8] {
+    [0xA1, 0x01, 0x00, selector, 0x00, 0x0A, 0x02, 0x00]
+}
+
+#[test]
+fn clock_multiplier_reports_its_ratio() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .clock_multiplier(256, 1)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // bClockID, bCSourceID the clock source, numerator and denominator read
+    // only
+    let descriptor = control_descriptors(&config).into_iter().find(|d| d[2] == 0x0C).unwrap();
+    assert_eq!(descriptor, [0x07, 0x24, 0x0C, 0x0A, 0x01, 0b0000_0101, 0x00]);
+
+    assert_eq!(control_in(&mut device, &mut class, multiplier(0x01)).unwrap(), 256u16.to_le_bytes());
+    assert_eq!(control_in(&mut device, &mut class, multiplier(0x02)).unwrap(), 1u16.to_le_bytes());
+
+    // firmware reconfigures the PLL
+    class.set_clock_multiplier(512, 2).unwrap();
+    assert_eq!(control_in(&mut device, &mut class, multiplier(0x01)).unwrap(), 512u16.to_le_bytes());
+    assert_eq!(control_in(&mut device, &mut class, multiplier(0x02)).unwrap(), 2u16.to_le_bytes());
+}
+
+#[test]
+fn clock_multiplier_must_be_added() {
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator())
+        .unwrap();
+
+    assert!(matches!(class.set_clock_multiplier(2, 1), Err(Error::ControlNotEnabled)));
+}
+
+#[test]
+fn frequency_is_truncated_to_w_length() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // GET_CUR of the sampling frequency control asking for 2 of its 4 bytes,
+    // and for more than it has
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x01, 0x00, 0x01, 0x02, 0x00]).unwrap(), [0x80, 0xBB]);
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x01, 0x00, 0x01, 0x08, 0x00]).unwrap(), 48000u32.to_le_bytes());
+}
+
+// bmAttributes and bmControls of the Clock Source descriptor
+fn clock_bits(source: ClockSource<'static>) -> (u8, u8) {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .clock_source(source)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+    let clock = control_descriptors(&config).into_iter().find(|d| d[2] == 0x0A).unwrap();
+
+    (clock[4], clock[5])
+}
+
+#[test]
+fn clock_source_configurations() {
+    // internal fixed, frequency read only, validity read only
+    assert_eq!(clock_bits(ClockSource::new()), (0b001, 0b0101));
+
+    // external and synchronized to SOF
+    assert_eq!(clock_bits(ClockSource::new().clock_type(ClockType::External).sof_sync(true)), (0b100, 0b0101));
+
+    // a choice of rates makes the frequency host programmable
+    assert_eq!(
+        clock_bits(ClockSource::new().clock_type(ClockType::InternalProgrammable).rates(&[44100, 48000])),
+        (0b011, 0b0111)
+    );
+
+    // without the frequency control only validity is left
+    assert_eq!(clock_bits(ClockSource::new().clock_type(ClockType::InternalVariable).frequency_control(false)), (0b010, 0b0100));
+}
+
+#[test]
+fn named_clock_has_a_string() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .clock_source(ClockSource::new().name("Crystal"))
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // iClockSource
+    let index = control_descriptors(&config).into_iter().find(|d| d[2] == 0x0A).unwrap()[7];
+    assert_ne!(index, 0);
+
+    let descriptor = control_in(&mut device, &mut class, [0x80, 0x06, index, 0x03, 0x09, 0x04, 0xFF, 0x00]).unwrap();
+    let units: Vec<u16> = descriptor[2..].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    assert_eq!(String::from_utf16(&units).unwrap(), "Crystal");
+
+}
+
+// GET_CUR and SET_CUR of the clock selector control on interface 0
+const GET_SELECTOR: [u8; 8] = [0xA1, 0x01, 0x00, 0x01, 0x00, 0x0B, 0x01, 0x00];
+const SET_SELECTOR: [u8; 8] = [0x21, 0x01, 0x00, 0x01, 0x00, 0x0B, 0x01, 0x00];
+
+#[test]
+fn clock_selector_chooses_between_sources() {
+    static SOURCES: [ClockSource<'static>; 2] = [ClockSource::new(), ClockSource::new()];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .clock_selector(&SOURCES)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+    let control = control_descriptors(&config);
+
+    // bClockID, bNrInPins, baCSourceID of each pin, bmControls, iClockSelector
+    let selector = control.iter().find(|d| d[2] == 0x0B).unwrap();
+    assert_eq!(*selector, [10, 0x24, 0x0B, 0x0B, 0x03, 0x01, 0x0C, 0x0D, 0x03, 0x00]);
+
+    let clocks: Vec<u8> = control.iter().filter(|d| d[2] == 0x0A).map(|d| d[3]).collect();
+    assert_eq!(clocks, [0x01, 0x0C, 0x0D]);
+
+    // both terminals are clocked by the selector: bCSourceID
+    assert_eq!(control.iter().find(|d| d[2] == 0x02).unwrap()[7], 0x0B);
+    assert_eq!(control.iter().find(|d| d[2] == 0x03).unwrap()[8], 0x0B);
+
+    // pin 1, the main clock, is selected until the host chooses another
+    assert_eq!(class.selected_clock(), Some(1));
+    assert_eq!(control_in(&mut device, &mut class, GET_SELECTOR).unwrap(), [1]);
+
+    assert!(control_out(&mut device, &mut class, SET_SELECTOR, &[3]));
+    assert_eq!(class.selected_clock(), Some(3));
+    assert_eq!(control_in(&mut device, &mut class, GET_SELECTOR).unwrap(), [3]);
+
+    // there is no fourth pin
+    assert!(!control_out(&mut device, &mut class, SET_SELECTOR, &[4]));
+    assert_eq!(class.selected_clock(), Some(3));
+}
+
+#[test]
+fn stream_follows_the_selected_clock() {
+    static RATES: [u32; 2] = [44100, 48000];
+    static SOURCES: [ClockSource<'static>; 1] = [ClockSource::new().rates(&RATES)];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .clock_selector(&SOURCES)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // the second source runs at 44.1 kHz, the main one stays at 48 kHz
+    assert!(control_out(&mut device, &mut class, [0x21, 0x01, 0x00, 0x01, 0x00, 0x0C, 0x04, 0x00], &44100u32.to_le_bytes()));
+    assert_eq!(class.bytes_per_frame(UsbDirection::In).unwrap(), 192);
+
+    assert!(control_out(&mut device, &mut class, SET_SELECTOR, &[2]));
+    assert_eq!(class.bytes_per_frame(UsbDirection::In).unwrap(), 176);
+
+    assert!(control_out(&mut device, &mut class, SET_SELECTOR, &[1]));
+    assert_eq!(class.bytes_per_frame(UsbDirection::In).unwrap(), 192);
+}
+
+#[test]
+fn clock_selector_takes_at_most_three_sources() {
+    static SOURCES: [ClockSource<'static>; 4] = [ClockSource::new(), ClockSource::new(), ClockSource::new(), ClockSource::new()];
+
+    let class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .clock_selector(&SOURCES)
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::TooManyClockSources)));
+}
+
+#[test]
+fn clock_validity_is_read_only() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // D3..2 of bmControls: the validity control can be read but not set
+    assert_eq!(clock_controls(&config)[0] >> 2 & 0b11, 0b01);
+
+    // SET_CUR stalls and GET_CUR keeps reporting what firmware set
+    class.set_clock_valid(0x01, true).unwrap();
+    assert!(!control_out(&mut device, &mut class, [0x21, 0x01, 0x00, 0x02, 0x00, 0x01, 0x01, 0x00], &[0]));
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [1]);
+
+    class.set_clock_valid(0x01, false).unwrap();
+    assert_eq!(control_in(&mut device, &mut class, VALIDITY).unwrap(), [0]);
+}
+
+#[test]
+fn frequency_range_is_the_same_on_every_request() {
+    static RATES: [u32; 2] = [44100, 48000];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .sample_rates(&RATES)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // RANGE of the sampling frequency control of clock 0x01 on interface 0
+    let range = |length: u8| [0xA1, 0x02, 0x00, 0x01, 0x00, 0x01, length, 0x00];
+
+    // wNumSubRanges, then MIN, MAX and RES of each discrete rate
+    let mut block = vec![0x02, 0x00];
+    for rate in RATES {
+        block.extend(rate.to_le_bytes());
+        block.extend(rate.to_le_bytes());
+        block.extend(1u32.to_le_bytes());
+    }
+
+    // a host asking for the count first, then for all of it, then retrying
+    assert_eq!(control_in(&mut device, &mut class, range(2)).unwrap(), block[..2]);
+    assert_eq!(control_in(&mut device, &mut class, range(26)).unwrap(), block);
+    assert_eq!(control_in(&mut device, &mut class, range(26)).unwrap(), block);
+    assert_eq!(control_in(&mut device, &mut class, range(2)).unwrap(), block[..2]);
+}
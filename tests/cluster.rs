@@ -0,0 +1,64 @@
+mod common;
+
+use usb_device::class::UsbClass;
+use usbd_audio_2::{AudioClassBuilder, Cluster, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// GET_CUR of the cluster control of the output stream's input terminal
+const CLUSTER: [u8; 8] = [0xA1, 0x01, 0x00, 0x04, 0x00, 0x04, 0x06, 0x00];
+
+#[test]
+fn cluster_control_reports_the_updated_cluster() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .cluster_control(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // bNrChannels, bmChannelConfig, iChannelNames
+    assert_eq!(control_in(&mut device, &mut class, CLUSTER).unwrap(), [2, 0, 0, 0, 0, 0]);
+
+    // a 5.1 set of speakers is connected
+    class.set_output_cluster(Cluster { n_channels: 6, channel_config: 0x3F }).unwrap();
+    assert_eq!(control_in(&mut device, &mut class, CLUSTER).unwrap(), [6, 0x3F, 0, 0, 0, 0]);
+}
+
+#[test]
+fn cluster_control_must_be_enabled() {
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator())
+        .unwrap();
+
+    assert!(matches!(class.set_output_cluster(Cluster { n_channels: 1, channel_config: 0 }), Err(Error::ControlNotEnabled)));
+}
+
+#[test]
+fn cluster_change_reports_a_full_status_queue() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .cluster_control(true)
+        .status_interrupt(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // the host does not poll the status endpoint, so the queue fills up
+    for n_channels in 3..7 {
+        class.set_output_cluster(Cluster { n_channels, channel_config: 0 }).unwrap();
+    }
+
+    let cluster = Cluster { n_channels: 8, channel_config: 0 };
+    assert!(matches!(class.set_output_cluster(cluster), Err(Error::StatusQueueFull)));
+
+    // the cluster itself still changed
+    assert_eq!(control_in(&mut device, &mut class, CLUSTER).unwrap()[0], 8);
+
+    // once a message goes out there is room again
+    class.poll();
+    class.set_output_cluster(Cluster { n_channels: 2, channel_config: 0 }).unwrap();
+}
@@ -0,0 +1,222 @@
+//! An in-memory `UsbBus` for driving the class through `UsbDevice::poll`:
+//! control transfers are fed in and collected by hand, every other endpoint
+//! is allocated in order and records what the class writes to it
+//!
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+
+use usb_device::bus::PollResult;
+use usb_device::class_prelude::*;
+use usb_device::endpoint::EndpointType;
+use usb_device::prelude::*;
+use usb_device::UsbDirection;
+
+#[derive(Default)]
+struct State {
+    next_ep: usize,
+    setup: Option<[u8; 8]>,
+    out_data: Vec<u8>,
+    packet: Option<Vec<u8>>,
+    in_data: Vec<u8>,
+    in_complete: bool,
+    stalled: bool,
+    written: Vec<(usize, Vec<u8>)>,
+}
+
+thread_local!(static STATE: RefCell<State> = RefCell::new(State::default()));
+
+fn state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with(|s| f(&mut s.borrow_mut()))
+}
+
+pub struct MockBus;
+
+impl UsbBus for MockBus {
+    fn alloc_ep(&mut self, dir: UsbDirection, ep: Option<EndpointAddress>, _ep_type: EndpointType, _max_packet_size: u16, _interval: u8) -> usb_device::Result<EndpointAddress> {
+        if let Some(ep) = ep {
+            return Ok(ep);
+        }
+
+        state(|s| {
+            if s.next_ep >= 15 {
+                return Err(UsbError::EndpointOverflow);
+            }
+
+            s.next_ep += 1;
+            Ok(EndpointAddress::from_parts(s.next_ep, dir))
+        })
+    }
+
+    fn enable(&mut self) {}
+
+    fn reset(&self) {}
+
+    fn set_device_address(&self, _addr: u8) {}
+
+    fn write(&self, ep: EndpointAddress, buf: &[u8]) -> usb_device::Result<usize> {
+        if ep.index() == 0 {
+            state(|s| {
+                s.in_data.extend_from_slice(buf);
+                s.in_complete = true;
+            });
+        } else {
+            state(|s| s.written.push((ep.index(), buf.to_vec())));
+        }
+
+        Ok(buf.len())
+    }
+
+    fn read(&self, ep: EndpointAddress, buf: &mut [u8]) -> usb_device::Result<usize> {
+        state(|s| {
+            let data = if ep.index() == 0 {
+                match s.setup.take() {
+                    Some(setup) => setup.to_vec(),
+                    None => core::mem::take(&mut s.out_data),
+                }
+            } else {
+                s.packet.take().ok_or(UsbError::WouldBlock)?
+            };
+
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        })
+    }
+
+    fn set_stalled(&self, ep: EndpointAddress, stalled: bool) {
+        if ep.index() == 0 && stalled {
+            state(|s| s.stalled = true);
+        }
+    }
+
+    fn is_stalled(&self, _ep: EndpointAddress) -> bool {
+        false
+    }
+
+    fn suspend(&self) {}
+
+    fn resume(&self) {}
+
+    fn poll(&self) -> PollResult {
+        state(|s| {
+            if s.setup.is_some() {
+                PollResult::Data { ep_out: 0, ep_in_complete: 0, ep_setup: 1 }
+            } else if !s.out_data.is_empty() {
+                PollResult::Data { ep_out: 1, ep_in_complete: 0, ep_setup: 0 }
+            } else if s.in_complete {
+                s.in_complete = false;
+                PollResult::Data { ep_out: 0, ep_in_complete: 1, ep_setup: 0 }
+            } else {
+                PollResult::None
+            }
+        })
+    }
+}
+
+/// A fresh allocator for one class under test
+pub fn allocator() -> &'static UsbBusAllocator<MockBus> {
+    state(|s| *s = State::default());
+
+    Box::leak(Box::new(UsbBusAllocator::new(MockBus)))
+}
+
+pub fn device(allocator: &'static UsbBusAllocator<MockBus>) -> UsbDevice<'static, MockBus> {
+    UsbDeviceBuilder::new(allocator, UsbVidPid(0x1209, 0x0001))
+        .max_packet_size_0(64)
+        .unwrap()
+        .build()
+}
+
+fn run(device: &mut UsbDevice<MockBus>, class: &mut dyn UsbClass<MockBus>) {
+    for _ in 0..32 {
+        device.poll(&mut [&mut *class]);
+    }
+}
+
+/// Issue a control IN request, returning the data or None if it stalled
+pub fn control_in(device: &mut UsbDevice<MockBus>, class: &mut dyn UsbClass<MockBus>, setup: [u8; 8]) -> Option<Vec<u8>> {
+    state(|s| {
+        s.setup = Some(setup);
+        s.in_data.clear();
+        s.stalled = false;
+    });
+
+    run(device, class);
+
+    state(|s| if s.stalled { None } else { Some(s.in_data.clone()) })
+}
+
+/// Issue a control OUT request with `data`, returning whether it was accepted
+pub fn control_out(device: &mut UsbDevice<MockBus>, class: &mut dyn UsbClass<MockBus>, setup: [u8; 8], data: &[u8]) -> bool {
+    state(|s| {
+        s.setup = Some(setup);
+        s.in_data.clear();
+        s.stalled = false;
+    });
+
+    device.poll(&mut [&mut *class]);
+    state(|s| s.out_data = data.to_vec());
+    run(device, class);
+
+    state(|s| !s.stalled)
+}
+
+/// Queue a packet for the next read of a non-control endpoint
+pub fn receive(packet: &[u8]) {
+    state(|s| s.packet = Some(packet.to_vec()));
+}
+
+/// Take the packets the class wrote to non-control endpoint `index`
+pub fn written(index: usize) -> Vec<Vec<u8>> {
+    state(|s| {
+        let (taken, rest) = core::mem::take(&mut s.written).into_iter().partition(|(ep, _)| *ep == index);
+        s.written = rest;
+
+        taken.into_iter().map(|(_, packet)| packet).collect()
+    })
+}
+
+/// GET_DESCRIPTOR of the configuration descriptor
+pub fn configuration(device: &mut UsbDevice<MockBus>, class: &mut dyn UsbClass<MockBus>) -> Vec<u8> {
+    control_in(device, class, [0x80, 0x06, 0x00, 0x02, 0x00, 0x00, 0xFF, 0x03]).unwrap()
+}
+
+/// SET_INTERFACE of `interface` to `alt`
+pub fn set_interface(device: &mut UsbDevice<MockBus>, class: &mut dyn UsbClass<MockBus>, interface: u8, alt: u8) -> bool {
+    control_in(device, class, [0x01, 0x0B, alt, 0x00, interface, 0x00, 0x00, 0x00]).is_some()
+}
+
+/// Split a descriptor set into its descriptors, checking each bLength
+pub fn descriptors(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut descriptors = Vec::new();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let length = rest[0] as usize;
+        assert!(length >= 2 && length <= rest.len(), "bad bLength {length}");
+
+        descriptors.push(&rest[..length]);
+        rest = &rest[length..];
+    }
+
+    descriptors
+}
+
+/// The class-specific descriptors of the AudioControl interface: the header,
+/// clock entities, terminals and units
+pub fn control_descriptors(config: &[u8]) -> Vec<&[u8]> {
+    let mut in_control = false;
+
+    descriptors(config)
+        .into_iter()
+        .filter(|d| {
+            // INTERFACE: audio class, AudioControl subclass
+            if d[1] == 0x04 {
+                in_control = d[5] == 0x01 && d[6] == 0x01;
+            }
+
+            in_control && d[1] == 0x24
+        })
+        .collect()
+}
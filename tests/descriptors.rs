@@ -0,0 +1,758 @@
+mod common;
+
+use usb_device::endpoint::{IsochronousSynchronizationType, IsochronousUsageType};
+use usbd_audio_2::{AudioClass, AudioClassBuilder, AudioProfile, Category, ChannelConfig, Cluster, EndpointAttributes, Error, Format, InterfaceOrder, StreamConfig, TerminalType};
+
+use common::*;
+
+// Check the configuration wTotalLength against the bytes sent and the AC
+// header's wTotalLength against the class-specific AC descriptors that follow
+// it, whichever audio class revision the header declares
+fn check_lengths(config: &[u8]) {
+    assert_eq!(u16::from_le_bytes([config[2], config[3]]) as usize, config.len());
+
+    let mut in_control = false;
+    let mut header_total = None;
+    let mut control_len = 0;
+
+    for descriptor in descriptors(config) {
+        match descriptor[1] {
+            // INTERFACE: audio class, AudioControl subclass
+            0x04 => in_control = descriptor[5] == 0x01 && descriptor[6] == 0x01,
+            // CS_INTERFACE
+            0x24 if in_control => {
+                if descriptor[2] == 0x01 {
+                    // 1.0 has no bCategory before wTotalLength
+                    let at = if descriptor[4] == 0x01 { 5 } else { 6 };
+                    header_total = Some(u16::from_le_bytes([descriptor[at], descriptor[at + 1]]) as usize);
+                }
+                control_len += descriptor.len();
+            }
+            _ => {}
+        }
+    }
+
+    assert_eq!(header_total, Some(control_len));
+}
+
+fn config_of(class: &mut AudioClass<'static, MockBus>, allocator: &'static usb_device::bus::UsbBusAllocator<MockBus>) -> Vec<u8> {
+    let mut device = device(allocator);
+    configuration(&mut device, class)
+}
+
+#[test]
+fn feature_unit_lengths() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .mute_control(true)
+        .volume_control(true)
+        .channel_mute_control(true)
+        .channel_volume_control(true)
+        .build(allocator)
+        .unwrap();
+
+    check_lengths(&config_of(&mut class, allocator));
+}
+
+#[test]
+fn input_sink_lengths() {
+    static RATES: [u32; 3] = [44100, 48000, 96000];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .input_sink(TerminalType::ExtDigitalAudioInterface)
+        .sample_rates(&RATES)
+        .build(allocator)
+        .unwrap();
+
+    check_lengths(&config_of(&mut class, allocator));
+}
+
+#[test]
+fn mixer_unit_lengths() {
+    static LINE: [TerminalType; 1] = [TerminalType::ExtLineConnector];
+    static MIX: [TerminalType; 2] = [TerminalType::Raw(0x0603), TerminalType::ExtDigitalAudioInterface];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .input_selector(&LINE)
+        .mixer_unit(&MIX)
+        .mute_control(true)
+        .build(allocator)
+        .unwrap();
+
+    check_lengths(&config_of(&mut class, allocator));
+}
+
+#[test]
+fn audio_1_0_lengths() {
+    static RATES: [u32; 3] = [44100, 48000, 96000];
+
+    for sync in [IsochronousSynchronizationType::Asynchronous, IsochronousSynchronizationType::Adaptive] {
+        let allocator = common::allocator();
+        let mut class = AudioClassBuilder::new()
+            .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+            .output(StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+            .output_endpoint_attributes(EndpointAttributes { sync, usage: IsochronousUsageType::Data, max_packets_only: false })
+            .input_sink(TerminalType::ExtDigitalAudioInterface)
+            .sample_rates(&RATES)
+            .audio_1_0(true)
+            .build(allocator)
+            .unwrap();
+
+        let config = config_of(&mut class, allocator);
+
+        // bcdADC 1.00 in the AC header
+        assert!(descriptors(&config).iter().any(|d| d[1] == 0x24 && d[2] == 0x01 && d[3..5] == [0x00, 0x01]));
+        check_lengths(&config);
+    }
+}
+
+#[test]
+fn status_interrupt_lengths() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .independent_clocks(true)
+        .status_interrupt(true)
+        .mute_control(true)
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+
+    // the interrupt endpoint is a standard descriptor, outside the AC total
+    assert!(descriptors(&config).iter().any(|d| d[1] == 0x05 && d[3] & 0x03 == 0x03));
+    check_lengths(&config);
+}
+
+// bCategory of the AudioControl header
+fn category(config: &[u8]) -> u8 {
+    let header = descriptors(config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x01).unwrap();
+
+    header[5]
+}
+
+#[test]
+fn category_is_written_to_the_header() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutHeadphones, 48000).unwrap())
+        .category(Category::Headset)
+        .build(allocator)
+        .unwrap();
+
+    assert_eq!(category(&config_of(&mut class, allocator)), 0x04);
+}
+
+#[test]
+fn category_defaults_to_desktop_speaker_for_output_only() {
+    let allocator = allocator();
+    let mut speaker = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert_eq!(category(&config_of(&mut speaker, allocator)), 0x01);
+}
+
+#[test]
+fn category_defaults_to_io_box_otherwise() {
+    let allocator = allocator();
+    let mut microphone = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert_eq!(category(&config_of(&mut microphone, allocator)), 0x08);
+}
+
+#[test]
+fn headset_category_needs_headphones() {
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .category(Category::Headset);
+
+    assert!(matches!(builder.validate_category(), Err(Error::CategoryMismatch)));
+    assert!(matches!(builder.strict_category(true).build(allocator()), Err(Error::CategoryMismatch)));
+
+    // only a hint unless strict
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .category(Category::Headset);
+    assert!(builder.build(allocator()).is_ok());
+
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutHeadphones, 48000).unwrap())
+        .category(Category::Headset)
+        .strict_category(true);
+    assert!(builder.validate_category().is_ok());
+    assert!(builder.build(allocator()).is_ok());
+}
+
+#[test]
+fn raw_terminal_types() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::Raw(0x0501), 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    // an unlisted code, here a telephony handset, goes out as given
+    let terminal = control_descriptors(&config_of(&mut class, allocator)).into_iter().find(|d| d[2] == 0x02).unwrap().to_vec();
+    assert_eq!(u16::from_le_bytes([terminal[4], terminal[5]]), 0x0501);
+
+    // and a raw code of a listed type is classified as that type
+    assert_eq!(TerminalType::Raw(0x0302).code(), TerminalType::OutHeadphones.code());
+    let builder = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::Raw(0x0302), 48000).unwrap())
+        .category(Category::Headset);
+    assert!(builder.validate_category().is_ok());
+}
+
+#[test]
+fn input_sink_is_a_non_usb_output_terminal() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .input_sink(TerminalType::ExtDigitalAudioInterface)
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+    let terminals: Vec<&[u8]> = control_descriptors(&config).into_iter().filter(|d| d[2] == 0x03).collect();
+
+    // the USB streaming terminal, then the sink: bTerminalID, wTerminalType,
+    // bSourceID the microphone, bCSourceID the clock
+    assert_eq!(terminals.len(), 2);
+    assert_eq!(terminals[0][4..6], [0x01, 0x01]);
+    assert_eq!(terminals[1][3], 0x07);
+    assert_eq!(terminals[1][4..6], [0x02, 0x06]);
+    assert_eq!(terminals[1][7], 0x02);
+    assert_eq!(terminals[1][8], 0x01);
+}
+
+#[test]
+fn every_streaming_interface_starts_with_an_idle_alt_0() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+
+    // AudioStreaming INTERFACE descriptors: bInterfaceNumber,
+    // bAlternateSetting, bNumEndpoints, bInterfaceProtocol
+    let settings: Vec<(u8, u8, u8, u8)> = descriptors(&config)
+        .into_iter()
+        .filter(|d| d[1] == 0x04 && d[5] == 0x01 && d[6] == 0x02)
+        .map(|d| (d[2], d[3], d[4], d[7]))
+        .collect();
+
+    // the output stream also has its feedback endpoint
+    assert_eq!(settings, [(1, 0, 0, 0x20), (1, 1, 1, 0x20), (2, 0, 0, 0x20), (2, 1, 2, 0x20)]);
+}
+
+#[test]
+fn endpoint_attributes_follow_the_allocated_type() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+    let attributes: Vec<u8> = descriptors(&config).into_iter().filter(|d| d[1] == 0x05).map(|d| d[3]).collect();
+
+    // isochronous: asynchronous implicit feedback data in, asynchronous data
+    // out, and the explicit feedback endpoint with no synchronization
+    assert_eq!(attributes, [0b0010_0101, 0b0000_0101, 0b0001_0001]);
+}
+
+#[test]
+fn extra_descriptor_is_appended_and_counted() {
+    static VENDOR: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    let allocator = allocator();
+    let mut plain = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let plain = config_of(&mut plain, allocator);
+
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .extra_descriptor(0xFF, &VENDOR)
+        .build(allocator)
+        .unwrap();
+    let config = config_of(&mut class, allocator);
+
+    assert_eq!(*descriptors(&config).last().unwrap(), [0x06, 0xFF, 0xDE, 0xAD, 0xBE, 0xEF]);
+
+    // the configuration's wTotalLength grows by the descriptor
+    let total_length = |config: &[u8]| u16::from_le_bytes([config[2], config[3]]) as usize;
+    assert_eq!(total_length(&config), config.len());
+    assert_eq!(total_length(&config), total_length(&plain) + 6);
+}
+
+#[test]
+fn input_terminal_cluster_is_independent_of_the_stream() {
+    let allocator = allocator();
+    let array = Cluster { n_channels: 4, channel_config: 0 };
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophoneArray, 48000).unwrap().with_terminal_cluster(array))
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+
+    // the microphone array's input terminal: bNrChannels
+    let terminal = control_descriptors(&config).into_iter().find(|d| d[2] == 0x02 && d[3] == 0x02).unwrap();
+    assert_eq!(terminal[8], 4);
+
+    // the beamformed stream on USB, AS_GENERAL: bNrChannels
+    let general = descriptors(&config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x01 && d.len() == 16).unwrap();
+    assert_eq!(general[10], 1);
+}
+
+// bAssocTerminal of every terminal, by bTerminalID
+fn associations(config: &[u8]) -> Vec<(u8, u8)> {
+    control_descriptors(config).into_iter().filter(|d| d[2] == 0x02 || d[2] == 0x03).map(|d| (d[3], d[6])).collect()
+}
+
+#[test]
+fn headset_terminals_are_associated() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutHeadphones, 48000).unwrap())
+        .associate_terminals(true)
+        .build(allocator)
+        .unwrap();
+
+    // the microphone names the headphones and the headphones the microphone,
+    // the USB streaming terminals stay unassociated
+    assert_eq!(associations(&config_of(&mut class, allocator)), [(0x02, 0x05), (0x03, 0x00), (0x04, 0x00), (0x05, 0x02)]);
+}
+
+#[test]
+fn terminals_are_unassociated_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutHeadphones, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert!(associations(&config_of(&mut class, allocator)).iter().all(|(_, assoc)| *assoc == 0x00));
+}
+
+// bmAttributes of the input stream's data endpoint
+fn input_attributes(config: &[u8]) -> u8 {
+    descriptors(config).into_iter().find(|d| d[1] == 0x05 && d[2] & 0x80 != 0 && d[3] & 0x03 == 0x01).unwrap()[3]
+}
+
+#[test]
+fn input_endpoint_usage_follows_implicit_feedback() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    // isochronous, asynchronous, implicit feedback data
+    assert_eq!(input_attributes(&config_of(&mut class, allocator)), 0b0010_0101);
+
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .implicit_feedback(false)
+        .build(allocator)
+        .unwrap();
+
+    // plain data, and still no feedback endpoint of its own
+    let config = config_of(&mut class, allocator);
+    assert_eq!(input_attributes(&config), 0b0000_0101);
+    assert_eq!(descriptors(&config).into_iter().filter(|d| d[1] == 0x05 && d[3] & 0x30 == 0x10).count(), 0);
+}
+
+// GET_DESCRIPTOR of string `index`, decoded
+fn string(device: &mut usb_device::device::UsbDevice<'static, MockBus>, class: &mut AudioClass<'static, MockBus>, index: u8) -> String {
+    let descriptor = control_in(device, class, [0x80, 0x06, index, 0x03, 0x09, 0x04, 0xFF, 0x00]).unwrap();
+    let units: Vec<u16> = descriptor[2..].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+
+    String::from_utf16(&units).unwrap()
+}
+
+#[test]
+fn terminal_and_channel_names_have_their_own_strings() {
+    static CHANNELS: [&str; 2] = ["Left", "Right"];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(
+            StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000)
+                .unwrap()
+                .with_terminal_name("Boom Mic")
+                .with_channel_names(&CHANNELS)
+        )
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // the microphone's input terminal: iChannelNames, iTerminal
+    let terminal = control_descriptors(&config).into_iter().find(|d| d[2] == 0x02 && d[3] == 0x02).unwrap().to_vec();
+    let (channel_names, terminal_name) = (terminal[13], terminal[16]);
+
+    assert_ne!(channel_names, 0);
+    assert_ne!(terminal_name, 0);
+    assert_ne!(channel_names, terminal_name);
+
+    // one string per logical channel, from iChannelNames on
+    assert_eq!(string(&mut device, &mut class, terminal_name), "Boom Mic");
+    assert_eq!(string(&mut device, &mut class, channel_names), "Left");
+    assert_eq!(string(&mut device, &mut class, channel_names + 1), "Right");
+}
+
+// bInterfaceNumber and bInterfaceSubClass of every INTERFACE descriptor for
+// alternate setting 0, in the order they are written
+fn interfaces(config: &[u8]) -> Vec<(u8, u8)> {
+    descriptors(config).into_iter().filter(|d| d[1] == 0x04 && d[3] == 0).map(|d| (d[2], d[6])).collect()
+}
+
+// bFirstInterface and bInterfaceCount of the interface association
+fn association(config: &[u8]) -> (u8, u8) {
+    let iad = descriptors(config).into_iter().find(|d| d[1] == 0x0B).unwrap();
+
+    (iad[2], iad[3])
+}
+
+#[test]
+fn interface_order_moves_the_streaming_interfaces() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let config = config_of(&mut class, allocator);
+
+    // AudioControl, then the two AudioStreaming interfaces
+    assert_eq!(interfaces(&config), [(0, 0x01), (1, 0x02), (2, 0x02)]);
+    assert_eq!(association(&config), (0, 3));
+
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .interface_order(InterfaceOrder::StreamingFirst)
+        .build(allocator)
+        .unwrap();
+    let config = config_of(&mut class, allocator);
+
+    // the descriptors move but keep their numbers, and the association
+    // still starts at the AudioControl interface and covers all three
+    assert_eq!(interfaces(&config), [(1, 0x02), (2, 0x02), (0, 0x01)]);
+    assert_eq!(association(&config), (0, 3));
+    assert_eq!(descriptors(&config).iter().position(|d| d[1] == 0x0B), Some(1));
+}
+
+#[test]
+fn association_starts_at_the_control_interface() {
+    let allocator = allocator();
+    // another class's interface, taken before the audio function's
+    let _other = allocator.interface();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    let control = class.control_interface_number();
+    assert_eq!(control, 1);
+
+    // the control and both streams
+    assert_eq!(association(&config_of(&mut class, allocator)), (control, 3));
+}
+
+#[test]
+fn formats_sharing_a_subslot_combine_their_bm_formats() {
+    static FORMATS: [Format; 2] = [Format::F32LE, Format::S16LE];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S32LE, 2, TerminalType::OutSpeaker, 48000).unwrap().with_formats(&FORMATS).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // AS_GENERAL bmFormats: PCM and IEEE_FLOAT at alt 1, PCM at alt 2
+    let bm_formats: Vec<u32> = descriptors(&config)
+        .into_iter()
+        .filter(|d| d[1] == 0x24 && d[2] == 0x01 && d.len() == 16)
+        .map(|d| u32::from_le_bytes([d[6], d[7], d[8], d[9]]))
+        .collect();
+    assert_eq!(bm_formats, [0b101, 0b001]);
+
+    // one FORMAT_TYPE per subslot: bSubslotSize, bBitResolution
+    let slots: Vec<(u8, u8)> = descriptors(&config)
+        .into_iter()
+        .filter(|d| d[1] == 0x24 && d[2] == 0x02 && d.len() == 6)
+        .map(|d| (d[4], d[5]))
+        .collect();
+    assert_eq!(slots, [(4, 32), (2, 16)]);
+
+    // the selected alternate setting picks the format
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    assert_eq!(class.output_format().unwrap(), Format::S32LE);
+    assert!(set_interface(&mut device, &mut class, 1, 2));
+    assert_eq!(class.output_format().unwrap(), Format::S16LE);
+}
+
+#[test]
+fn endpoint_attributes_map_to_bm_attributes() {
+    let attributes = |sync, usage, max_packets_only| {
+        let attributes = EndpointAttributes { sync, usage, max_packets_only };
+        (attributes.bm_attributes(), attributes.cs_bm_attributes())
+    };
+
+    // isochronous in bits 1..0, sync type in 3..2, usage type in 5..4, and
+    // MaxPacketsOnly in bit 7 of the class-specific descriptor
+    assert_eq!(attributes(IsochronousSynchronizationType::Asynchronous, IsochronousUsageType::Data, false), (0b0000_0101, 0x00));
+    assert_eq!(attributes(IsochronousSynchronizationType::Asynchronous, IsochronousUsageType::ImplicitFeedbackData, false), (0b0010_0101, 0x00));
+    assert_eq!(attributes(IsochronousSynchronizationType::Adaptive, IsochronousUsageType::Data, true), (0b0000_1001, 0x80));
+    assert_eq!(attributes(IsochronousSynchronizationType::Synchronous, IsochronousUsageType::Data, true), (0b0000_1101, 0x80));
+    assert_eq!(attributes(IsochronousSynchronizationType::NoSynchronization, IsochronousUsageType::Data, false), (0b0000_0001, 0x00));
+}
+
+// bNumEndpoints of the output stream's alt 1, and the bmAttributes of the
+// endpoints written after it
+fn output_endpoints(config: &[u8]) -> (u8, Vec<u8>) {
+    let descriptors = descriptors(config);
+    let alt = descriptors.iter().position(|d| d[1] == 0x04 && d[2] == 1 && d[3] == 1).unwrap();
+    let endpoints = descriptors[alt..].iter().filter(|d| d[1] == 0x05).map(|d| d[3]).collect();
+
+    (descriptors[alt][4], endpoints)
+}
+
+#[test]
+fn asynchronous_output_has_a_feedback_endpoint() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert_eq!(output_endpoints(&config_of(&mut class, allocator)), (2, vec![0b0000_0101, 0b0001_0001]));
+    assert!(class.write_feedback(48000).is_ok());
+}
+
+#[test]
+fn adaptive_output_has_no_feedback_endpoint() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .output_endpoint_attributes(EndpointAttributes {
+            sync: IsochronousSynchronizationType::Adaptive,
+            usage: IsochronousUsageType::Data,
+            max_packets_only: false,
+        })
+        .build(allocator)
+        .unwrap();
+
+    let config = config_of(&mut class, allocator);
+    assert_eq!(output_endpoints(&config), (1, vec![0b0000_1001]));
+    assert_eq!(u16::from_le_bytes([config[2], config[3]]) as usize, config.len());
+    assert!(matches!(class.write_feedback(48000), Err(Error::StreamNotInitialized)));
+}
+
+#[test]
+fn type_i_format_descriptor_of_each_format() {
+    for (format, subslot, resolution) in [(Format::U8, 1, 8), (Format::S16LE, 2, 16), (Format::S24LE, 3, 24), (Format::S32LE, 4, 32), (Format::F32LE, 4, 32)] {
+        let allocator = allocator();
+        let mut class = AudioClassBuilder::new()
+            .output(StreamConfig::new(format, 2, TerminalType::OutSpeaker, 48000).unwrap())
+            .build(allocator)
+            .unwrap();
+        let config = config_of(&mut class, allocator);
+
+        // FORMAT_TYPE_I: bSubslotSize, bBitResolution
+        let format_type = descriptors(&config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x02 && d.len() == 6).unwrap();
+        assert_eq!(*format_type, [0x06, 0x24, 0x02, 0x01, subslot, resolution], "{format:?}");
+    }
+}
+
+// a configuration descriptor less its own 9 byte header
+fn contribution(class: &mut AudioClass<'static, MockBus>, allocator: &'static usb_device::bus::UsbBusAllocator<MockBus>) -> (usize, usize) {
+    (class.config_contribution_len(), config_of(class, allocator).len() - 9)
+}
+
+#[test]
+fn config_contribution_len_matches_the_bytes_written() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let (expected, written) = contribution(&mut class, allocator);
+    assert_eq!(expected, written);
+
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .independent_clocks(true)
+        .status_interrupt(true)
+        .mute_control(true)
+        .volume_control(true)
+        .build(allocator)
+        .unwrap();
+    let (expected, written) = contribution(&mut class, allocator);
+    assert_eq!(expected, written);
+
+    // an alt setting per format, a clock multiplier and a vendor descriptor
+    static FORMATS: [Format; 1] = [Format::S24LE];
+    static VENDOR: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap().with_formats(&FORMATS).unwrap())
+        .clock_multiplier(2, 1)
+        .extra_descriptor(0xFF, &VENDOR)
+        .build(allocator)
+        .unwrap();
+    let (expected, written) = contribution(&mut class, allocator);
+    assert_eq!(expected, written);
+
+    // without the feedback endpoint
+    let allocator = common::allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .output_endpoint_attributes(EndpointAttributes {
+            sync: IsochronousSynchronizationType::Adaptive,
+            usage: IsochronousUsageType::Data,
+            max_packets_only: false,
+        })
+        .build(allocator)
+        .unwrap();
+    let (expected, written) = contribution(&mut class, allocator);
+    assert_eq!(expected, written);
+}
+
+// bmChannelConfig of the input terminals and of the AS_GENERAL descriptors
+fn channel_configs(config: &[u8]) -> (Vec<u32>, Vec<u32>) {
+    let terminals = control_descriptors(config)
+        .into_iter()
+        .filter(|d| d[2] == 0x02)
+        .map(|d| u32::from_le_bytes([d[9], d[10], d[11], d[12]]))
+        .collect();
+    let general = descriptors(config)
+        .into_iter()
+        .filter(|d| d[1] == 0x24 && d.len() == 16 && d[2] == 0x01)
+        .map(|d| u32::from_le_bytes([d[11], d[12], d[13], d[14]]))
+        .collect();
+
+    (terminals, general)
+}
+
+#[test]
+fn channel_config_is_written_to_terminals_and_as_general() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(
+            StreamConfig::new(Format::S16LE, 3, TerminalType::InMicrophone, 48000)
+                .unwrap()
+                .with_channel_config(ChannelConfig::stereo() | ChannelConfig::LOW_FREQUENCY_EFFECTS)
+        )
+        .build(allocator)
+        .unwrap();
+
+    // the microphone, the only input terminal without an output stream, and
+    // the stream's AS_GENERAL: front left and right plus LFE
+    assert_eq!(channel_configs(&config_of(&mut class, allocator)), (vec![0b1011], vec![0b1011]));
+}
+
+#[test]
+fn profiles_locate_their_channels() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .profile(AudioProfile::Headset, 48000)
+        .unwrap()
+        .build(allocator)
+        .unwrap();
+
+    // a mono microphone front center, stereo headphones front left and right
+    let (terminals, general) = channel_configs(&config_of(&mut class, allocator));
+    assert_eq!(terminals, [0b100, 0b011]);
+    assert_eq!(general, [0b100, 0b011]);
+}
+
+#[test]
+fn descriptor_size_matches_the_configuration() {
+    for sync in [IsochronousSynchronizationType::Asynchronous, IsochronousSynchronizationType::Adaptive] {
+        let builder = || {
+            AudioClassBuilder::new()
+                .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+                .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+                .output_endpoint_attributes(EndpointAttributes { sync, usage: IsochronousUsageType::Data, max_packets_only: false })
+                .mute_control(true)
+        };
+
+        let allocator = common::allocator();
+        let mut class = builder().build(allocator).unwrap();
+        let length = config_of(&mut class, allocator).len();
+
+        assert!(builder().validate_descriptor_size(length).is_ok());
+        assert!(matches!(
+            builder().validate_descriptor_size(length - 1),
+            Err(Error::DescriptorOverflow { required, available }) if required == length && available == length - 1
+        ));
+    }
+}
+
+#[test]
+fn control_buffer_size_is_checked_by_build() {
+    let class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .control_buffer_size(128)
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::DescriptorOverflow { available: 128, .. })));
+}
+
+// (bInterfaceNumber, bAlternateSetting, bNumEndpoints) of every
+// AudioStreaming INTERFACE descriptor
+fn streaming_alt_settings(config: &[u8]) -> Vec<(u8, u8, u8)> {
+    descriptors(config).into_iter().filter(|d| d[1] == 0x04 && d[6] == 0x02).map(|d| (d[2], d[3], d[4])).collect()
+}
+
+#[test]
+fn each_stream_has_a_zero_bandwidth_alt_0() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    // alt 0 without endpoints, then alt 1 with the data endpoint, and for the
+    // output its feedback endpoint
+    assert_eq!(streaming_alt_settings(&config_of(&mut class, allocator)), [(1, 0, 0), (1, 1, 1), (2, 0, 0), (2, 1, 2)]);
+}
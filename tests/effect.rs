@@ -0,0 +1,109 @@
+mod common;
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use usbd_audio_2::{AudioClassBuilder, EffectAccess, EffectRequest, EffectType, EffectUnit, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// Answers GET_CUR of the reverb type control with a fixed value and accepts
+// any value for it, everything else stalls
+fn reverb(request: EffectRequest, access: EffectAccess) -> Option<usize> {
+    if request.selector != 0x02 {
+        return None;
+    }
+
+    match access {
+        EffectAccess::Get(buf) => {
+            buf[0] = 0x03;
+            Some(1)
+        }
+        EffectAccess::Set(data) => (data == [0x03]).then_some(0),
+    }
+}
+
+#[test]
+fn effect_unit_sits_between_the_output_stream_and_terminal() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .effect_unit(EffectUnit::new(EffectType::Reverberation, 0b1100, reverb))
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+    let control = control_descriptors(&config);
+
+    // bUnitID, wEffectType, bSourceID the USB streaming terminal, the master
+    // bmaControls, one empty bmaControls per channel, iEffects
+    let effect = control.iter().find(|d| d[2] == 0x07).unwrap();
+    assert_eq!(*effect, [
+        20, 0x24, 0x07, 0x08, 0x02, 0x00, 0x04,
+        0x0C, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ]);
+
+    // the output terminal takes its audio from the unit
+    let terminal = control.iter().find(|d| d[2] == 0x03).unwrap();
+    assert_eq!(terminal[7], 0x08);
+
+    // wTotalLength covers the unit
+    let total: usize = control.iter().map(|d| d.len()).sum();
+    assert_eq!(u16::from_le_bytes([control[0][6], control[0][7]]) as usize, total);
+}
+
+#[test]
+fn effect_requests_go_to_the_handler() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .effect_unit(EffectUnit::new(EffectType::Reverberation, 0b1100, reverb))
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // CUR of RV_TYPE_CONTROL, master channel, unit 8 on interface 0
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x02, 0x00, 0x08, 0x01, 0x00]).unwrap(), [0x03]);
+    assert!(control_out(&mut device, &mut class, [0x21, 0x01, 0x00, 0x02, 0x00, 0x08, 0x01, 0x00], &[0x03]));
+    assert!(!control_out(&mut device, &mut class, [0x21, 0x01, 0x00, 0x02, 0x00, 0x08, 0x01, 0x00], &[0x01]));
+
+    // a control the handler does not know stalls
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x00, 0x05, 0x00, 0x08, 0x01, 0x00]), None);
+}
+
+// Sampled by firmware from a slow peripheral, outside of any control transfer
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+fn cached(_request: EffectRequest, access: EffectAccess) -> Option<usize> {
+    match access {
+        EffectAccess::Get(buf) => {
+            buf[0] = LEVEL.load(Ordering::Relaxed);
+            Some(1)
+        }
+        EffectAccess::Set(_) => None,
+    }
+}
+
+#[test]
+fn get_cur_answers_from_a_firmware_cache() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .effect_unit(EffectUnit::new(EffectType::ParametricEq, 0b01, cached))
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // CUR of the first control, master channel, unit 8 on interface 0
+    let get = [0xA1, 0x01, 0x00, 0x01, 0x00, 0x08, 0x01, 0x00];
+
+    // the main loop samples the peripheral between transfers, and each
+    // transfer is answered at once from the latest sample
+    LEVEL.store(0x20, Ordering::Relaxed);
+    assert_eq!(control_in(&mut device, &mut class, get).unwrap(), [0x20]);
+
+    LEVEL.store(0x40, Ordering::Relaxed);
+    assert_eq!(control_in(&mut device, &mut class, get).unwrap(), [0x40]);
+}
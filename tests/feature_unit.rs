@@ -0,0 +1,174 @@
+mod common;
+
+use usb_device::device::UsbDevice;
+use usbd_audio_2::{AudioClass, AudioClassBuilder, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+const CUR: u8 = 0x01;
+const RANGE: u8 = 0x02;
+const MUTE: u8 = 0x01;
+const VOLUME: u8 = 0x02;
+
+// GET and SET of a master channel control of the feature unit, entity 0x09 on
+// interface 0
+fn get(request: u8, selector: u8, length: u8) -> [u8; 8] {
+    [0xA1, request, 0x00, selector, 0x00, 0x09, length, 0x00]
+}
+
+fn set(selector: u8, length: u8) -> [u8; 8] {
+    [0x21, CUR, 0x00, selector, 0x00, 0x09, length, 0x00]
+}
+
+fn speaker(mute: bool, volume: bool) -> (AudioClass<'static, MockBus>, UsbDevice<'static, MockBus>) {
+    let allocator = allocator();
+    let class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .mute_control(mute)
+        .volume_control(volume)
+        .build(allocator)
+        .unwrap();
+
+    (class, device(allocator))
+}
+
+// bmaControls of the master channel of the feature unit
+fn master_controls(device: &mut UsbDevice<'static, MockBus>, class: &mut AudioClass<'static, MockBus>) -> u32 {
+    let config = configuration(device, class);
+    let unit = control_descriptors(&config).into_iter().find(|d| d[2] == 0x06).unwrap();
+
+    u32::from_le_bytes([unit[5], unit[6], unit[7], unit[8]])
+}
+
+#[test]
+fn mute_and_volume_are_advertised_separately() {
+    let (mut class, mut device) = speaker(true, false);
+    assert_eq!(master_controls(&mut device, &mut class), 0b0011);
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, VOLUME, 2)), None);
+
+    let (mut class, mut device) = speaker(false, true);
+    assert_eq!(master_controls(&mut device, &mut class), 0b1100);
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, MUTE, 1)), None);
+
+    let (mut class, mut device) = speaker(true, true);
+    assert_eq!(master_controls(&mut device, &mut class), 0b1111);
+}
+
+#[test]
+fn mute_and_volume_answer_their_own_selectors() {
+    let (mut class, mut device) = speaker(true, true);
+
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, MUTE, 1)).unwrap(), [0x00]);
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, VOLUME, 2)).unwrap(), 0i16.to_le_bytes());
+
+    // muting leaves the volume alone
+    assert!(control_out(&mut device, &mut class, set(MUTE, 1), &[0x01]));
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, MUTE, 1)).unwrap(), [0x01]);
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, VOLUME, 2)).unwrap(), 0i16.to_le_bytes());
+
+    // and -20 dB leaves the mute alone
+    assert!(control_out(&mut device, &mut class, set(VOLUME, 2), &(-20i16 * 256).to_le_bytes()));
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, VOLUME, 2)).unwrap(), (-20i16 * 256).to_le_bytes());
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, MUTE, 1)).unwrap(), [0x01]);
+}
+
+#[test]
+fn volume_range_is_given_and_mute_range_stalls() {
+    let (mut class, mut device) = speaker(true, true);
+
+    // one subrange, -60 dB to 0 dB in 1 dB steps
+    let range = [0x01, 0x00, 0x00, 0xC4, 0x00, 0x00, 0x00, 0x01];
+    assert_eq!(control_in(&mut device, &mut class, get(RANGE, VOLUME, 8)).unwrap(), range);
+
+    // mute is a boolean control
+    assert_eq!(control_in(&mut device, &mut class, get(RANGE, MUTE, 8)), None);
+}
+
+#[test]
+fn volume_is_clamped_to_its_range() {
+    let (mut class, mut device) = speaker(false, true);
+
+    assert!(control_out(&mut device, &mut class, set(VOLUME, 2), &(-90i16 * 256).to_le_bytes()));
+    assert_eq!(control_in(&mut device, &mut class, get(CUR, VOLUME, 2)).unwrap(), (-60i16 * 256).to_le_bytes());
+}
+
+#[test]
+fn master_volume_follows_set_cur() {
+    let (class, _) = speaker(false, false);
+    assert_eq!(class.volume(), None);
+
+    let (mut class, mut device) = speaker(false, true);
+    assert_eq!(class.volume(), Some(0));
+
+    // the host drags the slider to -12.5 dB
+    assert!(control_out(&mut device, &mut class, set(VOLUME, 2), &(-12 * 256 - 128i16).to_le_bytes()));
+    assert_eq!(class.volume(), Some(-12 * 256 - 128));
+}
+
+#[test]
+fn mute_follows_set_cur() {
+    let (class, _) = speaker(false, false);
+    assert_eq!(class.mute(), None);
+
+    let (mut class, mut device) = speaker(true, false);
+    assert_eq!(class.mute(), Some(false));
+
+    assert!(control_out(&mut device, &mut class, set(MUTE, 1), &[0x01]));
+    assert_eq!(class.mute(), Some(true));
+
+    assert!(control_out(&mut device, &mut class, set(MUTE, 1), &[0x00]));
+    assert_eq!(class.mute(), Some(false));
+}
+
+#[test]
+fn mute_only_channel_2() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .mute_control(true)
+        .channel_mute_control(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    // master mute, then a mute control on each logical channel
+    let config = configuration(&mut device, &mut class);
+    let unit = control_descriptors(&config).into_iter().find(|d| d[2] == 0x06).unwrap();
+    assert_eq!(unit[5..17], [0b11, 0, 0, 0, 0b11, 0, 0, 0, 0b11, 0, 0, 0]);
+
+    // CUR of the mute control of channel 2
+    assert!(control_out(&mut device, &mut class, [0x21, 0x01, 0x02, 0x01, 0x00, 0x09, 0x01, 0x00], &[0x01]));
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x02, 0x01, 0x00, 0x09, 0x01, 0x00]).unwrap(), [0x01]);
+    assert_eq!(control_in(&mut device, &mut class, [0xA1, 0x01, 0x01, 0x01, 0x00, 0x09, 0x01, 0x00]).unwrap(), [0x00]);
+    assert_eq!(class.channel_muted(1), Some(false));
+    assert_eq!(class.channel_muted(2), Some(true));
+
+    // the master mute silences every channel
+    assert!(control_out(&mut device, &mut class, set(MUTE, 1), &[0x01]));
+    assert_eq!(class.channel_muted(1), Some(true));
+
+    // no such channel
+    assert_eq!(class.channel_muted(3), None);
+    assert!(!control_out(&mut device, &mut class, [0x21, 0x01, 0x03, 0x01, 0x00, 0x09, 0x01, 0x00], &[0x01]));
+}
+
+// bSourceID of the output terminal and of the feature unit, if any
+fn sources(device: &mut UsbDevice<'static, MockBus>, class: &mut AudioClass<'static, MockBus>) -> (u8, Option<u8>) {
+    let config = configuration(device, class);
+    let control = control_descriptors(&config);
+    let terminal = control.iter().find(|d| d[2] == 0x03).unwrap();
+    let unit = control.iter().find(|d| d[2] == 0x06);
+
+    (terminal[7], unit.map(|d| d[4]))
+}
+
+#[test]
+fn output_terminal_sources_from_the_feature_unit() {
+    // USB streaming terminal 0x04 straight to the speaker
+    let (mut class, mut device) = speaker(false, false);
+    assert_eq!(sources(&mut device, &mut class), (0x04, None));
+
+    // USB streaming terminal 0x04 to feature unit 0x09 to the speaker
+    let (mut class, mut device) = speaker(true, true);
+    assert_eq!(sources(&mut device, &mut class), (0x09, Some(0x04)));
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use usbd_audio_2::{AudioClassBuilder, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// The endpoint number of the isochronous feedback endpoint
+fn feedback_endpoint(config: &[u8]) -> usize {
+    let endpoint = descriptors(config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x30 == 0x10).unwrap();
+
+    (endpoint[2] & 0x0F) as usize
+}
+
+#[test]
+fn last_feedback_returns_the_value_sent() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let endpoint = feedback_endpoint(&configuration(&mut device, &mut class));
+
+    assert_eq!(class.last_feedback(), None);
+
+    // 48 samples per frame in 10.14
+    assert_eq!(class.write_feedback(48000).unwrap(), 3);
+    assert_eq!(written(endpoint), [vec![0x00, 0x00, 0x0C]]);
+    assert_eq!(class.last_feedback(), Some(48000));
+
+    // 0x0B0666 falls just short of 44100 Hz
+    class.write_feedback(44100).unwrap();
+    assert_eq!(written(endpoint), [vec![0x66, 0x06, 0x0B]]);
+    assert!(class.last_feedback().unwrap().abs_diff(44100) <= 1);
+}
+
+#[test]
+fn last_feedback_at_high_speed() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .high_speed(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+    let endpoint = feedback_endpoint(&config);
+
+    // a 4 byte feedback endpoint, and a data endpoint holding 6 + 1 frames
+    // per microframe
+    let sizes: Vec<u16> = descriptors(&config)
+        .into_iter()
+        .filter(|d| d[1] == 0x05 && d[3] & 0x03 == 0x01)
+        .map(|d| u16::from_le_bytes([d[4], d[5]]))
+        .collect();
+    assert_eq!(sizes, [28, 4]);
+
+    // 12 samples per microframe in 16.16
+    assert_eq!(class.write_feedback(96000).unwrap(), 4);
+    assert_eq!(written(endpoint), [vec![0x00, 0x00, 0x0C, 0x00]]);
+    assert_eq!(class.last_feedback(), Some(96000));
+}
+
+#[test]
+fn fractional_rates_round_to_the_nearest_step() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 44100).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let endpoint = feedback_endpoint(&configuration(&mut device, &mut class));
+
+    // 44.1, 88.2 and 176.4 samples per frame are 722534.4, 1445068.8 and
+    // 2890137.6 in 10.14, the last two rounded up
+    for (rate, bytes) in [(44100, [0x66, 0x06, 0x0B]), (88200, [0xCD, 0x0C, 0x16]), (176400, [0x9A, 0x19, 0x2C])] {
+        class.write_feedback(rate).unwrap();
+        assert_eq!(written(endpoint), [bytes.to_vec()], "{rate} Hz");
+    }
+}
+
+#[test]
+fn last_feedback_without_an_output() {
+    let allocator = allocator();
+    let class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert!(class.write_feedback(48000).is_err());
+    assert_eq!(class.last_feedback(), None);
+}
+
+#[test]
+fn feedback_for_a_ppm_offset() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let endpoint = feedback_endpoint(&configuration(&mut device, &mut class));
+
+    // 100 PPM fast: 48.0048 samples per frame, 0x0C004F in 10.14
+    class.write_feedback_ppm(100).unwrap();
+    assert_eq!(written(endpoint), [vec![0x4F, 0x00, 0x0C]]);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use usbd_audio_2::{AudioClassBuilder, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// GET_CUR of the sampling frequency control of the clock
+const FREQUENCY: [u8; 8] = [0xA1, 0x01, 0x00, 0x01, 0x00, 0x01, 0x04, 0x00];
+
+// SET_CUR and GET_CUR of the copy protect control of the output terminal
+const SET_COPY_PROTECT: [u8; 8] = [0x21, 0x01, 0x00, 0x01, 0x00, 0x05, 0x01, 0x00];
+const GET_COPY_PROTECT: [u8; 8] = [0xA1, 0x01, 0x00, 0x01, 0x00, 0x05, 0x01, 0x00];
+
+#[test]
+fn control_transfers_interleave_with_streaming() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .copy_protect(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // the isochronous data endpoint of the input stream, the feedback
+    // endpoint has its usage bits set
+    let input = descriptors(&config)
+        .into_iter()
+        .find(|d| d[1] == 0x05 && d[2] & 0x80 != 0 && d[3] & 0x03 == 0x01 && d[3] & 0x30 != 0x10)
+        .map(|d| (d[2] & 0x0F) as usize)
+        .unwrap();
+
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    assert!(set_interface(&mut device, &mut class, 2, 1));
+
+    for i in 0..200u16 {
+        let [a, b] = i.to_le_bytes();
+        let packet = [a, b, !a, !b];
+
+        // a packet arrives from the host while control transfers come in
+        receive(&packet);
+        assert_eq!(control_in(&mut device, &mut class, FREQUENCY).unwrap(), 48000u32.to_le_bytes());
+
+        let level = (i % 3) as u8;
+        assert!(control_out(&mut device, &mut class, SET_COPY_PROTECT, &[level]));
+
+        let mut buf = [0u8; 192];
+        assert_eq!(class.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], packet);
+
+        // and a packet goes out between two control transfers
+        assert_eq!(class.write(&packet).unwrap(), 4);
+        assert_eq!(control_in(&mut device, &mut class, GET_COPY_PROTECT).unwrap(), [level]);
+        assert_eq!(written(input), [packet]);
+    }
+
+    // streaming left the alternate settings alone
+    assert_eq!(class.input_alt_setting().unwrap(), 1);
+    assert_eq!(class.output_alt_setting().unwrap(), 1);
+}
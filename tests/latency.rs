@@ -0,0 +1,45 @@
+mod common;
+
+use usbd_audio_2::{AudioClassBuilder, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// GET_CUR of the latency control of the AudioControl interface, entity 0 on
+// interface 0
+const LATENCY: [u8; 8] = [0xA1, 0x01, 0x00, 0x01, 0x00, 0x00, 0x04, 0x00];
+
+#[test]
+fn latency_control_is_advertised_and_reported() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .latency_control(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // AC header bmControls: latency read only
+    assert_eq!(control_descriptors(&config)[0][8], 0b01);
+
+    assert_eq!(control_in(&mut device, &mut class, LATENCY).unwrap(), 0u32.to_le_bytes());
+
+    // 1.5 ms through the codec and its buffers
+    class.set_function_latency(1_500_000).unwrap();
+    assert_eq!(control_in(&mut device, &mut class, LATENCY).unwrap(), 1_500_000u32.to_le_bytes());
+}
+
+#[test]
+fn latency_control_is_off_by_default() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    assert_eq!(control_descriptors(&config)[0][8], 0b00);
+    assert_eq!(control_in(&mut device, &mut class, LATENCY), None);
+    assert!(matches!(class.set_function_latency(1_500_000), Err(Error::ControlNotEnabled)));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use usbd_audio_2::{AudioClassBuilder, AudioProfile, Error};
+
+use common::*;
+
+// wTerminalType of every input and output terminal, and the bCategory of the
+// AudioControl header
+fn topology(profile: AudioProfile) -> (Vec<u16>, u8) {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .profile(profile, 48000)
+        .unwrap()
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+    let control = control_descriptors(&config);
+
+    let terminals = control.iter()
+        .filter(|d| d[2] == 0x02 || d[2] == 0x03)
+        .map(|d| u16::from_le_bytes([d[4], d[5]]))
+        .collect();
+
+    (terminals, control[0][5])
+}
+
+#[test]
+fn headset_profile() {
+    // microphone to USB, USB to headphones
+    assert_eq!(topology(AudioProfile::Headset), (vec![0x0201, 0x0101, 0x0101, 0x0302], 0x04));
+}
+
+#[test]
+fn speaker_profile() {
+    assert_eq!(topology(AudioProfile::Speaker), (vec![0x0101, 0x0301], 0x01));
+}
+
+#[test]
+fn microphone_profile() {
+    assert_eq!(topology(AudioProfile::Microphone), (vec![0x0201, 0x0101], 0x03));
+}
+
+#[test]
+fn profile_rejects_a_zero_rate() {
+    let builder = AudioClassBuilder::new().profile(AudioProfile::Headset, 0);
+
+    assert!(matches!(builder.err(), Some(Error::InvalidSampleRate)));
+}
+
+#[test]
+fn profile_rejects_an_oversized_rate() {
+    let builder = AudioClassBuilder::new().profile(AudioProfile::Speaker, 600_000_000);
+
+    assert!(matches!(builder.err(), Some(Error::BandwidthExceeded { .. })));
+}
+
+#[test]
+fn profile_checks_the_bus_speed_at_build() {
+    let class = AudioClassBuilder::new()
+        .profile(AudioProfile::Headset, 600000)
+        .unwrap()
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::BandwidthExceeded { available: 1023, .. })));
+}
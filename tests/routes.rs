@@ -0,0 +1,42 @@
+#![cfg(feature = "diagnostics")]
+
+mod common;
+
+use usb_device::UsbDirection;
+use usbd_audio_2::{AudioClassBuilder, ControlKind, ControlRoute, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+#[test]
+fn listed_routes_are_answered() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .copy_protect(true)
+        .cluster_control(true)
+        .alt_setting_controls(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    let mut routes: Vec<ControlRoute> = Vec::new();
+    class.control_routes(|route| routes.push(route));
+
+    let kinds = |kind: ControlKind| routes.iter().filter(|route| route.kind == kind).count();
+    assert_eq!(kinds(ControlKind::ClockRange), 1);
+    assert_eq!(kinds(ControlKind::ClockCur), 2);
+    assert_eq!(kinds(ControlKind::ClockValid), 1);
+    assert_eq!(kinds(ControlKind::CopyProtect), 2);
+    assert_eq!(kinds(ControlKind::Cluster), 1);
+    assert_eq!(kinds(ControlKind::AltSettings), 4);
+
+    // every GET the class lists gets an answer
+    for route in routes.iter().filter(|route| route.direction == UsbDirection::In) {
+        let [interface, entity] = route.index.to_le_bytes();
+        let setup = [0xA1, route.request, 0x00, route.selector.unwrap(), interface, entity, 0x40, 0x00];
+
+        assert!(control_in(&mut device, &mut class, setup).is_some(), "{route:?} unanswered");
+        assert_eq!(class.take_last_control(), Some(route.kind));
+    }
+}
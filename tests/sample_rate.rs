@@ -0,0 +1,52 @@
+mod common;
+
+use usb_device::UsbDirection;
+use usbd_audio_2::{AudioClassBuilder, Format, SampleRate, StreamConfig, TerminalType};
+
+use common::*;
+
+static RATES: [u32; 2] = [44100, 48000];
+
+// SET_CUR of the clock source's frequency control, entity 0x01 on interface 0
+fn set_rate(device: &mut usb_device::device::UsbDevice<MockBus>, class: &mut dyn usb_device::class::UsbClass<MockBus>, rate: u32) -> bool {
+    control_out(device, class, [0x21, 0x01, 0x00, 0x01, 0x00, 0x01, 0x04, 0x00], &rate.to_le_bytes())
+}
+
+#[test]
+fn stream_rate_follows_the_clock() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutHeadphones, 48000).unwrap())
+        .sample_rates(&RATES)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 192);
+
+    assert!(set_rate(&mut device, &mut class, 44100));
+    assert_eq!(class.current_sample_rate(), Some(SampleRate::Hz44100));
+    assert_eq!(class.bytes_per_frame(UsbDirection::In).unwrap(), 176);
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 176);
+
+    class.write_feedback_ppm(0).unwrap();
+    assert!(class.last_feedback().unwrap().abs_diff(44100) <= 1);
+}
+
+#[test]
+fn bytes_per_frame_uses_the_selected_format() {
+    static FORMATS: [Format; 1] = [Format::S24LE];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap().with_formats(&FORMATS).unwrap())
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 192);
+
+    assert!(set_interface(&mut device, &mut class, 1, 2));
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 288);
+}
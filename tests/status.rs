@@ -0,0 +1,64 @@
+mod common;
+
+use usb_device::class::UsbClass;
+use usbd_audio_2::{AudioClassBuilder, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// The endpoint number of the status interrupt endpoint
+fn status_endpoint(config: &[u8]) -> usize {
+    let endpoint = descriptors(config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x03 == 0x03).unwrap();
+
+    (endpoint[2] & 0x0F) as usize
+}
+
+#[test]
+fn control_changes_are_sent_in_order() {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .volume_control(true)
+        .status_interrupt(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let status = status_endpoint(&configuration(&mut device, &mut class));
+
+    // the feature unit's volume, then the output terminal's overload
+    class.notify_control_change(0x09, 0x02).unwrap();
+    class.notify_control_change(0x05, 0x04).unwrap();
+
+    // bInfo, bAttribute CUR, channel, control selector, interface, entity;
+    // one message per poll
+    class.poll();
+    assert_eq!(written(status), [vec![0x00, 0x01, 0x00, 0x02, 0x00, 0x09]]);
+    class.poll();
+    assert_eq!(written(status), [vec![0x00, 0x01, 0x00, 0x04, 0x00, 0x05]]);
+    class.poll();
+    assert!(written(status).is_empty());
+}
+
+#[test]
+fn control_change_needs_the_status_endpoint() {
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .build(allocator())
+        .unwrap();
+
+    assert!(matches!(class.notify_control_change(0x09, 0x02), Err(Error::ControlNotEnabled)));
+}
+
+#[test]
+fn control_change_reports_a_full_queue() {
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .status_interrupt(true)
+        .build(allocator())
+        .unwrap();
+
+    // four queue slots, no poll in between
+    for _ in 0..4 {
+        class.notify_control_change(0x09, 0x02).unwrap();
+    }
+    assert!(matches!(class.notify_control_change(0x09, 0x02), Err(Error::StatusQueueFull)));
+}
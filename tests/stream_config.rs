@@ -0,0 +1,266 @@
+mod common;
+
+use usb_device::UsbDirection;
+use usbd_audio_2::{AudioClassBuilder, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+#[test]
+fn alignment_pads_a_3_byte_sample_packet() {
+    // 49 stereo frames of 3 byte samples
+    let config = StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap();
+    assert_eq!(config.packet_size(), 294);
+
+    let config = config.with_alignment(4).unwrap();
+    assert_eq!(config.packet_size(), 296);
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(config)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // the isochronous data endpoint advertises the padded size
+    let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x03 == 0x01).unwrap();
+    assert_eq!(u16::from_le_bytes([endpoint[4], endpoint[5]]), 296);
+}
+
+#[test]
+fn alignment_must_be_a_power_of_two() {
+    let config = || StreamConfig::new(Format::S24LE, 2, TerminalType::OutSpeaker, 48000).unwrap();
+
+    assert!(matches!(config().with_alignment(3), Err(Error::InvalidAlignment)));
+    assert!(matches!(config().with_alignment(0), Err(Error::InvalidAlignment)));
+}
+
+#[test]
+fn resolution_below_the_container_size() {
+    let config = StreamConfig::new(Format::S32LE, 2, TerminalType::OutSpeaker, 48000)
+        .unwrap()
+        .with_resolution(20)
+        .unwrap();
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(config)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // Type I FORMAT_TYPE: bSubslotSize 4, bBitResolution 20
+    let format = descriptors(&config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x02 && d.len() == 6).unwrap();
+    assert_eq!(format, [0x06, 0x24, 0x02, 0x01, 0x04, 20]);
+}
+
+#[test]
+fn resolution_must_fit_the_container() {
+    let config = || StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap();
+
+    assert!(config().with_resolution(12).is_ok());
+    assert!(matches!(config().with_resolution(0), Err(Error::InvalidResolution)));
+    assert!(matches!(config().with_resolution(17), Err(Error::InvalidResolution)));
+}
+
+#[test]
+fn bytes_per_frame_of_48_khz_stereo() {
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap();
+
+    // 48 frames of two 2 byte samples; the endpoint leaves room for one more
+    assert_eq!(config.bytes_per_frame(), 192);
+    assert_eq!(config.packet_size(), 196);
+
+    let allocator = allocator();
+    let class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .output(config)
+        .build(allocator)
+        .unwrap();
+
+    assert_eq!(class.bytes_per_frame(UsbDirection::In).unwrap(), 192);
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 192);
+}
+
+#[test]
+fn bytes_per_frame_rounds_down() {
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 44100).unwrap();
+
+    // 44.1 frames on average, the endpoint holds 45 plus one of headroom
+    assert_eq!(config.bytes_per_frame(), 176);
+    assert_eq!(config.packet_size(), 184);
+}
+
+#[test]
+fn packet_size_rounds_fractional_rates_up() {
+    // frames per 1 ms frame rounded up, plus one of headroom, of two 2 byte
+    // samples
+    for (rate, size) in [(44100, 184), (88200, 360), (96000, 388)] {
+        let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, rate).unwrap();
+        assert_eq!(config.packet_size(), size, "{rate} Hz");
+    }
+}
+
+#[test]
+fn bytes_per_frame_without_a_stream() {
+    let allocator = allocator();
+    let class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 2, TerminalType::InMicrophone, 48000).unwrap())
+        .build(allocator)
+        .unwrap();
+
+    assert!(class.bytes_per_frame(UsbDirection::Out).is_err());
+}
+
+#[test]
+fn headroom_of_2_reaches_the_endpoint_size() {
+    // 48 frames plus 2 of headroom, of two 2 byte samples
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000)
+        .unwrap()
+        .with_headroom(2)
+        .unwrap();
+    assert_eq!(config.packet_size(), 200);
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(config)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x33 == 0x01).unwrap();
+    assert_eq!(u16::from_le_bytes([endpoint[4], endpoint[5]]), 200);
+}
+
+#[test]
+fn headroom_must_fit_a_full_speed_endpoint() {
+    // 48 + 16 frames of 8 2 byte samples, 1024 bytes, plus the feedback
+    // packet: fine at high speed, too much for a full speed class
+    let config = StreamConfig::new(Format::S16LE, 8, TerminalType::OutSpeaker, 48000)
+        .unwrap()
+        .with_headroom(16)
+        .unwrap();
+    let class = AudioClassBuilder::new()
+        .output(config)
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::BandwidthExceeded { required: 1027, available: 1023 })));
+}
+
+#[test]
+fn headroom_must_fit_a_high_speed_endpoint() {
+    // 6 + 64 frames per microframe of 8 2 byte samples, 1120 bytes
+    let config = StreamConfig::new(Format::S16LE, 8, TerminalType::OutSpeaker, 48000).unwrap();
+
+    assert!(matches!(config.with_headroom(64), Err(Error::BandwidthExceeded { required: 1120, available: 1024 })));
+}
+
+#[test]
+fn max_packet_size_follows_the_sample_rate() {
+    // a frame's worth of stereo 2 byte samples, plus one sample of headroom
+    for (rate, size) in [(44100, 184), (48000, 196), (96000, 388), (192000, 772)] {
+        let allocator = allocator();
+        let mut class = AudioClassBuilder::new()
+            .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, rate).unwrap())
+            .build(allocator)
+            .unwrap();
+        let mut device = device(allocator);
+        let config = configuration(&mut device, &mut class);
+
+        let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x33 == 0x01).unwrap();
+        assert_eq!(u16::from_le_bytes([endpoint[4], endpoint[5]]), size, "{rate} Hz");
+    }
+}
+
+#[test]
+fn mono_8_khz_u8_max_packet_size() {
+    // 8 one byte samples plus one of headroom
+    let config = StreamConfig::new(Format::U8, 1, TerminalType::InMicrophone, 8000).unwrap();
+    assert_eq!(config.packet_size(), 9);
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(config)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[2] & 0x80 != 0 && d[3] & 0x03 == 0x01).unwrap();
+    assert_eq!(u16::from_le_bytes([endpoint[4], endpoint[5]]), 9);
+
+    // AS_GENERAL bmFormats: PCM8
+    let general = descriptors(&config).into_iter().find(|d| d[1] == 0x24 && d[2] == 0x01 && d.len() == 16).unwrap();
+    assert_eq!(general[6..10], [0b10, 0, 0, 0]);
+}
+
+#[test]
+fn interval_sets_b_interval_and_the_packet_size() {
+    // two frames' worth of stereo 2 byte samples, plus one of headroom
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000)
+        .unwrap()
+        .with_interval(2)
+        .unwrap();
+    assert_eq!(config.packet_size(), 388);
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(config)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[3] & 0x33 == 0x01).unwrap();
+    assert_eq!(u16::from_le_bytes([endpoint[4], endpoint[5]]), 388);
+    assert_eq!(endpoint[6], 2);
+}
+
+#[test]
+fn interval_must_be_1_to_16() {
+    for interval in [0, 17] {
+        let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap();
+        assert!(matches!(config.with_interval(interval), Err(Error::InvalidInterval)));
+    }
+}
+
+#[test]
+fn new_rejects_no_channels() {
+    let config = StreamConfig::new(Format::S16LE, 0, TerminalType::OutSpeaker, 48000);
+
+    assert!(matches!(config.err(), Some(Error::InvalidChannelCount)));
+}
+
+#[test]
+fn new_rejects_255_channels() {
+    let config = StreamConfig::new(Format::S16LE, 255, TerminalType::OutSpeaker, 48000);
+
+    assert!(matches!(config.err(), Some(Error::InvalidChannelCount)));
+}
+
+#[test]
+fn new_rejects_a_zero_rate() {
+    let config = StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 0);
+
+    assert!(matches!(config.err(), Some(Error::InvalidSampleRate)));
+}
+
+#[test]
+fn new_rejects_a_packet_no_endpoint_holds() {
+    // 24 + 1 frames per microframe of 254 4 byte samples
+    let config = StreamConfig::new(Format::S32LE, 254, TerminalType::OutSpeaker, 192000);
+
+    assert!(matches!(config.err(), Some(Error::BandwidthExceeded { required: 25400, available: 1024 })));
+}
+
+// too much for a full speed frame, within a high speed microframe
+const SURROUND: StreamConfig<'static> = StreamConfig::const_new(Format::S32LE, 8, TerminalType::OutSpeaker, 192000);
+
+#[test]
+fn const_new_accepts_what_new_accepts() {
+    let config = StreamConfig::new(Format::S32LE, 8, TerminalType::OutSpeaker, 192000).unwrap();
+
+    assert_eq!(SURROUND.packet_size(), config.packet_size());
+}
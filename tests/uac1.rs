@@ -0,0 +1,56 @@
+mod common;
+
+use usb_device::UsbDirection;
+use usbd_audio_2::{AudioClassBuilder, Error, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+// GET_CUR and SET_CUR of the sampling frequency control of `endpoint`
+fn get_frequency(endpoint: u8) -> [u8; 8] {
+    [0xA2, 0x81, 0x00, 0x01, endpoint, 0x00, 0x03, 0x00]
+}
+
+fn set_frequency(endpoint: u8) -> [u8; 8] {
+    [0x22, 0x01, 0x00, 0x01, endpoint, 0x00, 0x03, 0x00]
+}
+
+#[test]
+fn host_sets_the_rate_through_the_endpoint() {
+    static RATES: [u32; 2] = [44100, 48000];
+
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .sample_rates(&RATES)
+        .audio_1_0(true)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // the isochronous OUT data endpoint
+    let endpoint = descriptors(&config).into_iter().find(|d| d[1] == 0x05 && d[2] & 0x80 == 0).unwrap()[2];
+
+    assert_eq!(control_in(&mut device, &mut class, get_frequency(endpoint)).unwrap(), [0x80, 0xBB, 0x00]);
+
+    assert!(control_out(&mut device, &mut class, set_frequency(endpoint), &[0x44, 0xAC, 0x00]));
+    assert_eq!(control_in(&mut device, &mut class, get_frequency(endpoint)).unwrap(), [0x44, 0xAC, 0x00]);
+
+    // the stream follows: 44 frames of two 2 byte samples
+    assert_eq!(class.bytes_per_frame(UsbDirection::Out).unwrap(), 176);
+
+    // a rate that is not offered
+    assert!(!control_out(&mut device, &mut class, set_frequency(endpoint), &[0x00, 0x77, 0x01]));
+    assert_eq!(control_in(&mut device, &mut class, get_frequency(endpoint)).unwrap(), [0x44, 0xAC, 0x00]);
+}
+
+#[test]
+fn features_without_a_1_0_counterpart_are_rejected() {
+    let class = AudioClassBuilder::new()
+        .output(StreamConfig::new(Format::S16LE, 2, TerminalType::OutSpeaker, 48000).unwrap())
+        .mute_control(true)
+        .audio_1_0(true)
+        .build(allocator());
+
+    assert!(matches!(class.err(), Some(Error::UnsupportedInAudio10)));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use usb_device::{class::UsbClass, endpoint::EndpointAddress, UsbDirection};
+use usbd_audio_2::{AudioClass, AudioClassBuilder, Format, StreamConfig, TerminalType};
+
+use common::*;
+
+fn microphone(zlp: bool) -> (AudioClass<'static, MockBus>, usb_device::device::UsbDevice<'static, MockBus>, usize) {
+    let allocator = allocator();
+    let mut class = AudioClassBuilder::new()
+        .input(StreamConfig::new(Format::S16LE, 1, TerminalType::InMicrophone, 48000).unwrap())
+        .input_zlp(zlp)
+        .build(allocator)
+        .unwrap();
+    let mut device = device(allocator);
+    let config = configuration(&mut device, &mut class);
+
+    // the isochronous data endpoint of the input stream
+    let endpoint = descriptors(&config)
+        .into_iter()
+        .find(|d| d[1] == 0x05 && d[2] & 0x80 != 0 && d[3] & 0x03 == 0x01)
+        .map(|d| (d[2] & 0x0F) as usize)
+        .unwrap();
+
+    (class, device, endpoint)
+}
+
+#[test]
+fn idle_poll_sends_a_zlp() {
+    let (mut class, mut device, endpoint) = microphone(true);
+
+    // nothing while the stream is closed
+    class.poll();
+    assert!(written(endpoint).is_empty());
+
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    class.poll();
+    assert_eq!(written(endpoint), [Vec::<u8>::new()]);
+
+    // one per frame, the next once the host has collected it
+    class.poll();
+    assert!(written(endpoint).is_empty());
+
+    class.endpoint_in_complete(EndpointAddress::from_parts(endpoint, UsbDirection::In));
+    class.poll();
+    assert_eq!(written(endpoint), [Vec::<u8>::new()]);
+}
+
+#[test]
+fn no_zlp_by_default() {
+    let (mut class, mut device, endpoint) = microphone(false);
+
+    assert!(set_interface(&mut device, &mut class, 1, 1));
+    class.poll();
+    assert!(written(endpoint).is_empty());
+}